@@ -1,8 +1,52 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::ops::Deref;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg(feature = "std")]
+pub use std::io::Write;
+
+/// Minimal stand-in for `std::io::Write` so encoding works without `std`.
+#[cfg(not(feature = "std"))]
+pub trait Write {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), BitcoinError>;
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), BitcoinError> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+/// Fills the slice from the front and advances past what was written, like
+/// std's impl for `&mut [u8]`.
+#[cfg(not(feature = "std"))]
+impl Write for &mut [u8] {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), BitcoinError> {
+        if buf.len() > self.len() {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let (head, tail) = core::mem::take(self).split_at_mut(buf.len());
+        head.copy_from_slice(buf);
+        *self = tail;
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CompactSize {
     pub value: u64,
 }
@@ -11,70 +55,608 @@ pub struct CompactSize {
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    #[cfg(feature = "std")]
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinError::InsufficientBytes => write!(f, "insufficient bytes to decode"),
+            BitcoinError::InvalidFormat => write!(f, "invalid format"),
+            #[cfg(feature = "std")]
+            BitcoinError::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BitcoinError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BitcoinError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => BitcoinError::InsufficientBytes,
+            kind => BitcoinError::Io(kind),
+        }
+    }
+}
+
+/// A decode failure annotated with where in the input it happened. Convert
+/// into `BitcoinError` to drop the context.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub field: &'static str,
+    pub error: BitcoinError,
+}
+
+impl DecodeError {
+    pub fn new(offset: usize, field: &'static str, error: BitcoinError) -> Self {
+        Self {
+            offset,
+            field,
+            error,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} while decoding {} at byte {}",
+            self.error, self.field, self.offset
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<DecodeError> for BitcoinError {
+    fn from(err: DecodeError) -> Self {
+        err.error
+    }
+}
+
+#[cfg(feature = "std")]
+fn read_array_from<const N: usize, R: Read>(reader: &mut R) -> Result<[u8; N], BitcoinError> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+// Slice readers. Each returns the value and the bytes it took, and fails
+// with `InsufficientBytes` rather than panicking when `bytes` is too short.
+
+fn read_array<const N: usize>(bytes: &[u8]) -> Result<([u8; N], usize), BitcoinError> {
+    let array = bytes
+        .first_chunk::<N>()
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    Ok((*array, N))
+}
+
+fn read_u16_le(bytes: &[u8]) -> Result<(u16, usize), BitcoinError> {
+    read_array(bytes).map(|(array, len)| (u16::from_le_bytes(array), len))
+}
+
+fn read_u32_le(bytes: &[u8]) -> Result<(u32, usize), BitcoinError> {
+    read_array(bytes).map(|(array, len)| (u32::from_le_bytes(array), len))
+}
+
+fn read_u64_le(bytes: &[u8]) -> Result<(u64, usize), BitcoinError> {
+    read_array(bytes).map(|(array, len)| (u64::from_le_bytes(array), len))
+}
+
+pub trait Encode {
+    /// Writes the consensus encoding to `writer`, returning the number of bytes written.
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        self.consensus_encode(&mut result)
+            .expect("writing to a Vec cannot fail");
+        result
+    }
+}
+
+/// `TryFrom<&[u8]>` decodes with `from_bytes` and, unlike it, rejects
+/// trailing bytes with `InvalidFormat`.
+macro_rules! impl_try_from_bytes {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<&[u8]> for $ty {
+            type Error = BitcoinError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, BitcoinError> {
+                let (value, consumed) = <$ty>::from_bytes(bytes)?;
+                if consumed != bytes.len() {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                Ok(value)
+            }
+        }
+    )*};
 }
 
+impl_try_from_bytes!(
+    CompactSize,
+    OutPoint,
+    Script,
+    TransactionInput,
+    BitcoinTransaction
+);
+
+/// Hex forms of the consensus encoding. `from_hex` must consume every byte
+/// and maps bad hex to `InvalidFormat`.
+macro_rules! impl_hex {
+    ($($ty:ty),*) => {$(
+        impl $ty {
+            pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+                let bytes = hex::decode(s).map_err(|_| BitcoinError::InvalidFormat)?;
+                Self::try_from(bytes.as_slice())
+            }
+
+            pub fn to_hex(&self) -> String {
+                hex::encode(self.to_bytes())
+            }
+        }
+    )*};
+}
+
+impl_hex!(OutPoint, TransactionInput, BitcoinTransaction);
+
 impl CompactSize {
     pub fn new(value: u64) -> Self {
         CompactSize { value }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
+    /// The value as a length or count. Fails where it does not fit, which
+    /// can happen on 32-bit targets.
+    pub fn to_usize(&self) -> Result<usize, BitcoinError> {
+        usize::try_from(self.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+
+    /// Same as `to_usize`.
+    pub fn value_as_usize(&self) -> Result<usize, BitcoinError> {
+        self.to_usize()
+    }
+
+    /// Bytes needed to encode this value: 1, 3, 5 or 9.
+    pub fn encoded_len(&self) -> usize {
+        match self.value {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x10000..=0xFFFFFFFF => 5,
+            _ => 9,
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        self.encoded_len()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.is_empty() {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+
+        let prefix = bytes[0];
+        let rest = &bytes[1..];
+        let (value, len) = match prefix {
+            0x00..=0xFC => (prefix as u64, 0),
+            0xFD => read_u16_le(rest).map(|(value, len)| (value as u64, len))?,
+            0xFE => read_u32_le(rest).map(|(value, len)| (value as u64, len))?,
+            0xFF => read_u64_le(rest)?,
+        };
+        Ok((CompactSize::new(value), 1 + len))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let [prefix] = read_array_from(reader)?;
+        Self::decode_after_prefix(prefix, reader)
+    }
+
+    #[cfg(feature = "std")]
+    fn decode_after_prefix<R: Read>(prefix: u8, reader: &mut R) -> Result<Self, BitcoinError> {
+        let value = match prefix {
+            0x00..=0xFC => prefix as u64,
+            0xFD => u16::from_le_bytes(read_array_from(reader)?) as u64,
+            0xFE => u32::from_le_bytes(read_array_from(reader)?) as u64,
+            0xFF => u64::from_le_bytes(read_array_from(reader)?),
+        };
+        Ok(CompactSize::new(value))
+    }
+
+    /// Like `from_bytes`, but rejects values that were not minimally encoded.
+    pub fn from_bytes_strict(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (size, consumed) = Self::from_bytes(bytes)?;
+        if size.to_bytes().len() != consumed {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((size, consumed))
+    }
+}
+
+impl From<u64> for CompactSize {
+    fn from(value: u64) -> Self {
+        CompactSize::new(value)
+    }
+}
+
+impl TryFrom<usize> for CompactSize {
+    type Error = BitcoinError;
+
+    fn try_from(value: usize) -> Result<Self, BitcoinError> {
+        u64::try_from(value)
+            .map(CompactSize::new)
+            .map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+impl Encode for CompactSize {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
         match self.value {
-            0..=0xFC => vec![self.value as u8],
+            0..=0xFC => {
+                writer.write_all(&[self.value as u8])?;
+                Ok(1)
+            }
             0xFD..=0xFFFF => {
-                let mut vec = vec![0xFD];
-                vec.extend_from_slice(&(self.value as u16).to_le_bytes());
-                vec
+                writer.write_all(&[0xFD])?;
+                writer.write_all(&(self.value as u16).to_le_bytes())?;
+                Ok(3)
             }
             0x10000..=0xFFFFFFFF => {
-                let mut vec = vec![0xFE];
-                vec.extend_from_slice(&(self.value as u32).to_le_bytes());
-                vec
+                writer.write_all(&[0xFE])?;
+                writer.write_all(&(self.value as u32).to_le_bytes())?;
+                Ok(5)
             }
             _ => {
-                let mut vec = vec![0xFF];
-                vec.extend_from_slice(&self.value.to_le_bytes());
-                vec
+                writer.write_all(&[0xFF])?;
+                writer.write_all(&self.value.to_le_bytes())?;
+                Ok(9)
             }
         }
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.is_empty() {
-            return Err(BitcoinError::InsufficientBytes);
+/// Renders the value with its wire size, e.g. `70000 (5 bytes)`.
+impl fmt::Display for CompactSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.encoded_len() {
+            1 => write!(f, "{} (1 byte)", self.value),
+            len => write!(f, "{} ({} bytes)", self.value, len),
         }
+    }
+}
 
-        let prefix = bytes[0];
-        match prefix {
-            0x00..=0xFC => Ok((CompactSize::new(prefix as u64), 1)),
-            0xFD => {
-                if bytes.len() < 3 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let val = u16::from_le_bytes(bytes[1..3].try_into().unwrap()) as u64;
-                Ok((CompactSize::new(val), 3))
+/// Shorthand for `CompactSize::new(value).to_bytes()`. `CompactSize` stays
+/// the canonical type; this is for callers with a bare count.
+pub fn encode_varint(value: u64) -> Vec<u8> {
+    CompactSize::new(value).to_bytes()
+}
+
+/// Shorthand for `CompactSize::from_bytes`, returning the bare value.
+pub fn decode_varint(bytes: &[u8]) -> Result<(u64, usize), BitcoinError> {
+    CompactSize::from_bytes(bytes).map(|(size, consumed)| (size.value, consumed))
+}
+
+/// Shorthand for `CompactSize::new(value).consensus_encode(writer)`.
+pub fn write_varint<W: Write>(writer: &mut W, value: u64) -> Result<usize, BitcoinError> {
+    CompactSize::new(value).consensus_encode(writer)
+}
+
+// Human-readable formats get a plain integer. Binary formats get the wire
+// encoding as a tuple of bytes, which formats like bincode write without a
+// length prefix.
+impl Serialize for CompactSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            return serializer.serialize_u64(self.value);
+        }
+        use serde::ser::SerializeTuple;
+        let bytes = self.to_bytes();
+        let mut tuple = serializer.serialize_tuple(bytes.len())?;
+        for byte in &bytes {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct WireVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for WireVisitor {
+            type Value = CompactSize;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CompactSize wire encoding")
             }
-            0xFE => {
-                if bytes.len() < 5 {
-                    return Err(BitcoinError::InsufficientBytes);
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<CompactSize, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut bytes = [0u8; 9];
+                bytes[0] = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                let len = match bytes[0] {
+                    0xFD => 3,
+                    0xFE => 5,
+                    0xFF => 9,
+                    _ => 1,
+                };
+                for (i, byte) in bytes.iter_mut().enumerate().take(len).skip(1) {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
                 }
-                let val = u32::from_le_bytes(bytes[1..5].try_into().unwrap()) as u64;
-                Ok((CompactSize::new(val), 5))
+                let (size, _) = CompactSize::from_bytes(&bytes[..len])
+                    .map_err(|_| serde::de::Error::custom("invalid CompactSize"))?;
+                Ok(size)
             }
-            0xFF => {
-                if bytes.len() < 9 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let val = u64::from_le_bytes(bytes[1..9].try_into().unwrap());
-                Ok((CompactSize::new(val), 9))
+        }
+
+        if deserializer.is_human_readable() {
+            u64::deserialize(deserializer).map(CompactSize::new)
+        } else {
+            deserializer.deserialize_tuple(9, WireVisitor)
+        }
+    }
+}
+
+/// Double SHA-256, the hash behind txids, wtxids, block hashes and merkle
+/// nodes. The result is in internal byte order.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// RIPEMD160(SHA256(data)), as used by P2PKH and P2SH.
+fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd::Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+// Smallest possible encodings: outpoint + empty script + sequence, and
+// value + empty script. Counts that cannot fit in the remaining buffer are
+// rejected before anything is allocated.
+const MIN_INPUT_SIZE: usize = 36 + 1 + 4;
+const MIN_OUTPUT_SIZE: usize = 8 + 1;
+// Version, one minimal input, no outputs, lock time.
+const MIN_TRANSACTION_SIZE: usize = 4 + 1 + MIN_INPUT_SIZE + 1 + 4;
+
+fn checked_count(
+    count: &CompactSize,
+    remaining: &[u8],
+    min_size: usize,
+) -> Result<usize, BitcoinError> {
+    let count = count.to_usize()?;
+    if count > remaining.len() / min_size {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(count)
+}
+
+// Readers cannot see how much input is left, so counts are bounded by what
+// could fit in `MAX_TRANSACTION_SIZE` bytes, and by `max_count`.
+#[cfg(feature = "std")]
+fn stream_count(
+    count: &CompactSize,
+    min_item_size: usize,
+    max_count: usize,
+) -> Result<usize, BitcoinError> {
+    let count = count.to_usize()?;
+    if count > MAX_TRANSACTION_SIZE / min_item_size || count > max_count {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(count)
+}
+
+/// Reads from `reader` through a `MAX_TRANSACTION_SIZE` byte budget, turning
+/// running out of budget into `InvalidFormat`.
+#[cfg(feature = "std")]
+fn decode_with_budget<R: Read, T>(
+    reader: &mut R,
+    decode: impl FnOnce(&mut io::Take<&mut R>) -> Result<T, BitcoinError>,
+) -> Result<T, BitcoinError> {
+    let mut limited = reader.take(MAX_TRANSACTION_SIZE as u64);
+    decode(&mut limited).map_err(|err| match err {
+        BitcoinError::InsufficientBytes if limited.limit() == 0 => BitcoinError::InvalidFormat,
+        err => err,
+    })
+}
+
+/// Decodes a CompactSize count followed by that many items, each read by
+/// `parse`, which returns the item and the bytes it consumed. A count too
+/// large for the remaining bytes is rejected before anything is allocated.
+pub fn decode_vec<T, F>(bytes: &[u8], parse: F) -> Result<(Vec<T>, usize), BitcoinError>
+where
+    F: FnMut(&[u8]) -> Result<(T, usize), BitcoinError>,
+{
+    let mut items = Vec::new();
+    let mut parse = parse;
+    let consumed = decode_vec_into(
+        bytes,
+        1,
+        usize::MAX,
+        ("count", "item"),
+        &mut items,
+        |item, _| parse(item),
+    )?;
+    Ok((items, consumed))
+}
+
+/// The `from_bytes` shape: an item and the bytes it consumed.
+pub type DecodeFn<T> = fn(&[u8]) -> Result<(T, usize), BitcoinError>;
+
+/// Lazily decodes `count` back-to-back items from a buffer positioned just
+/// after their CompactSize count, so long lists need not be collected. Stops
+/// after the first error.
+pub struct VecDecoder<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    remaining: usize,
+    parse: DecodeFn<T>,
+}
+
+impl<'a, T> VecDecoder<'a, T> {
+    pub fn new(bytes: &'a [u8], count: usize, parse: DecodeFn<T>) -> Self {
+        VecDecoder {
+            bytes,
+            offset: 0,
+            remaining: count,
+            parse,
+        }
+    }
+
+    /// Reads the CompactSize count from the front of `bytes` first.
+    pub fn from_prefixed(bytes: &'a [u8], parse: DecodeFn<T>) -> Result<Self, BitcoinError> {
+        let (count, prefix_len) = CompactSize::from_bytes(bytes)?;
+        Ok(VecDecoder {
+            bytes,
+            offset: prefix_len,
+            remaining: count.to_usize()?,
+            parse,
+        })
+    }
+
+    /// Bytes of the buffer consumed so far, including any count prefix.
+    pub fn consumed(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<T> Iterator for VecDecoder<'_, T> {
+    type Item = Result<T, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match (self.parse)(&self.bytes[self.offset..]) {
+            Ok((item, consumed)) => {
+                self.remaining -= 1;
+                self.offset += consumed;
+                Some(Ok(item))
+            }
+            Err(err) => {
+                self.remaining = 0;
+                Some(Err(err))
             }
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// `decode_vec`, but pushing onto `items` so that callers keep whatever was
+/// read before a failure. Counts above `max_count` are `InvalidFormat`.
+/// Errors name `fields.0` for the count and `fields.1` for an item, at
+/// offsets relative to `bytes`. `parse` also gets each item's offset in
+/// `bytes`.
+fn decode_vec_into<T, F>(
+    bytes: &[u8],
+    min_item_size: usize,
+    max_count: usize,
+    fields: (&'static str, &'static str),
+    items: &mut Vec<T>,
+    mut parse: F,
+) -> Result<usize, DecodeError>
+where
+    F: FnMut(&[u8], usize) -> Result<(T, usize), BitcoinError>,
+{
+    let count_error = |error| DecodeError::new(0, fields.0, error);
+    let (count, mut offset) = CompactSize::from_bytes(bytes).map_err(count_error)?;
+    let count = checked_count(&count, &bytes[offset..], min_item_size).map_err(count_error)?;
+    if count > max_count {
+        return Err(count_error(BitcoinError::InvalidFormat));
+    }
+    items.reserve(count);
+    for _ in 0..count {
+        let (item, consumed) = parse(&bytes[offset..], offset)
+            .map_err(|error| DecodeError::new(offset, fields.1, error))?;
+        items.push(item);
+        offset += consumed;
+    }
+    Ok(offset)
+}
+
+/// Transaction id stored in internal (little-endian) byte order, as it appears
+/// on the wire. Block explorers and RPC show the reversed, display-order form.
+/// Ordering compares the raw internal bytes, not the display form.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Txid(pub [u8; 32]);
 
+impl Txid {
+    pub fn to_display_string(&self) -> String {
+        let mut bytes = self.0;
+        bytes.reverse();
+        hex::encode(bytes)
+    }
+
+    /// Builds a txid from display-order (big-endian) bytes, as RPC and
+    /// explorers present them.
+    pub fn from_display_bytes(mut bytes: [u8; 32]) -> Txid {
+        bytes.reverse();
+        Txid(bytes)
+    }
+
+    pub fn from_display_string(s: &str) -> Result<Self, BitcoinError> {
+        let bytes = hex::decode(s).map_err(|_| BitcoinError::InvalidFormat)?;
+        let arr: [u8; 32] = bytes.try_into().map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Txid::from_display_bytes(arr))
+    }
+}
+
+/// The internal byte order, as used for hashing and on the wire.
+impl AsRef<[u8]> for Txid {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Txid {
+    fn from(bytes: [u8; 32]) -> Self {
+        Txid(bytes)
+    }
+}
+
+impl From<Txid> for [u8; 32] {
+    fn from(txid: Txid) -> Self {
+        txid.0
+    }
+}
+
+impl FromStr for Txid {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Txid::from_display_string(s)
+    }
+}
+
+// Serde keeps the internal byte order so JSON mirrors the wire bytes; use
+// `to_display_string` for the explorer-style form.
 impl Serialize for Txid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -100,7 +682,8 @@ impl<'de> Deserialize<'de> for Txid {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -114,145 +697,2042 @@ impl OutPoint {
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = self.txid.0.to_vec();
-        result.extend_from_slice(&self.vout.to_le_bytes());
-        result
+    /// The sentinel spent by coinbase inputs: an all-zero txid and vout
+    /// 0xFFFFFFFF.
+    pub fn null() -> Self {
+        OutPoint::new([0u8; 32], 0xFFFFFFFF)
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::null()
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        36
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let mut txid = [0u8; 32];
-        txid.copy_from_slice(&bytes[..32]);
-        let vout = u32::from_le_bytes(bytes[32..36].try_into().unwrap());
-        Ok((OutPoint::new(txid, vout), 36))
+        let (txid, txid_len) = read_array::<32>(bytes)?;
+        let (vout, vout_len) = read_u32_le(&bytes[txid_len..])?;
+        Ok((OutPoint::new(txid, vout), txid_len + vout_len))
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct Script {
-    pub bytes: Vec<u8>,
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let txid = read_array_from(reader)?;
+        let vout = u32::from_le_bytes(read_array_from(reader)?);
+        Ok(OutPoint::new(txid, vout))
+    }
 }
 
-impl Script {
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Script { bytes }
+impl Encode for OutPoint {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        writer.write_all(&self.txid.0)?;
+        writer.write_all(&self.vout.to_le_bytes())?;
+        Ok(36)
     }
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        result.extend_from_slice(&self.bytes);
-        result
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.txid.to_display_string(), self.vout)
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (size, size_len) = CompactSize::from_bytes(bytes)?;
-        let total_len = size_len + size.value as usize;
-        if bytes.len() < total_len {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let data = bytes[size_len..total_len].to_vec();
-        Ok((Script::new(data), total_len))
+impl FromStr for OutPoint {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (txid, vout) = s.split_once(':').ok_or(BitcoinError::InvalidFormat)?;
+        let txid = txid.parse::<Txid>()?;
+        let vout = vout
+            .parse::<u32>()
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(OutPoint { txid, vout })
     }
 }
 
-impl Deref for Script {
-    type Target = Vec<u8>;
-    fn deref(&self) -> &Self::Target {
-        &self.bytes
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4C;
+pub const OP_PUSHDATA2: u8 = 0x4D;
+pub const OP_PUSHDATA4: u8 = 0x4E;
+pub const OP_1NEGATE: u8 = 0x4F;
+pub const OP_1: u8 = 0x51;
+pub const OP_RETURN: u8 = 0x6A;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_HASH160: u8 = 0xA9;
+pub const OP_CHECKSIG: u8 = 0xAC;
+
+/// Consensus limit on the size of a script being executed.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+/// Consensus limit on a single pushed stack element.
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+
+const OPCODE_NAMES: [&str; 0xBB - 0x61] = [
+    "OP_NOP",
+    "OP_VER",
+    "OP_IF",
+    "OP_NOTIF",
+    "OP_VERIF",
+    "OP_VERNOTIF",
+    "OP_ELSE",
+    "OP_ENDIF",
+    "OP_VERIFY",
+    "OP_RETURN",
+    "OP_TOALTSTACK",
+    "OP_FROMALTSTACK",
+    "OP_2DROP",
+    "OP_2DUP",
+    "OP_3DUP",
+    "OP_2OVER",
+    "OP_2ROT",
+    "OP_2SWAP",
+    "OP_IFDUP",
+    "OP_DEPTH",
+    "OP_DROP",
+    "OP_DUP",
+    "OP_NIP",
+    "OP_OVER",
+    "OP_PICK",
+    "OP_ROLL",
+    "OP_ROT",
+    "OP_SWAP",
+    "OP_TUCK",
+    "OP_CAT",
+    "OP_SUBSTR",
+    "OP_LEFT",
+    "OP_RIGHT",
+    "OP_SIZE",
+    "OP_INVERT",
+    "OP_AND",
+    "OP_OR",
+    "OP_XOR",
+    "OP_EQUAL",
+    "OP_EQUALVERIFY",
+    "OP_RESERVED1",
+    "OP_RESERVED2",
+    "OP_1ADD",
+    "OP_1SUB",
+    "OP_2MUL",
+    "OP_2DIV",
+    "OP_NEGATE",
+    "OP_ABS",
+    "OP_NOT",
+    "OP_0NOTEQUAL",
+    "OP_ADD",
+    "OP_SUB",
+    "OP_MUL",
+    "OP_DIV",
+    "OP_MOD",
+    "OP_LSHIFT",
+    "OP_RSHIFT",
+    "OP_BOOLAND",
+    "OP_BOOLOR",
+    "OP_NUMEQUAL",
+    "OP_NUMEQUALVERIFY",
+    "OP_NUMNOTEQUAL",
+    "OP_LESSTHAN",
+    "OP_GREATERTHAN",
+    "OP_LESSTHANOREQUAL",
+    "OP_GREATERTHANOREQUAL",
+    "OP_MIN",
+    "OP_MAX",
+    "OP_WITHIN",
+    "OP_RIPEMD160",
+    "OP_SHA1",
+    "OP_SHA256",
+    "OP_HASH160",
+    "OP_HASH256",
+    "OP_CODESEPARATOR",
+    "OP_CHECKSIG",
+    "OP_CHECKSIGVERIFY",
+    "OP_CHECKMULTISIG",
+    "OP_CHECKMULTISIGVERIFY",
+    "OP_NOP1",
+    "OP_CHECKLOCKTIMEVERIFY",
+    "OP_CHECKSEQUENCEVERIFY",
+    "OP_NOP4",
+    "OP_NOP5",
+    "OP_NOP6",
+    "OP_NOP7",
+    "OP_NOP8",
+    "OP_NOP9",
+    "OP_NOP10",
+    "OP_CHECKSIGADD",
+];
+
+const SMALL_INT_NAMES: [&str; 16] = [
+    "OP_1", "OP_2", "OP_3", "OP_4", "OP_5", "OP_6", "OP_7", "OP_8", "OP_9", "OP_10", "OP_11",
+    "OP_12", "OP_13", "OP_14", "OP_15", "OP_16",
+];
+
+fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        OP_0 => "OP_0",
+        OP_1NEGATE => "OP_1NEGATE",
+        0x50 => "OP_RESERVED",
+        0x51..=0x60 => SMALL_INT_NAMES[(opcode - OP_1) as usize],
+        0x61..=0xBA => OPCODE_NAMES[(opcode - 0x61) as usize],
+        0xFF => "OP_INVALIDOPCODE",
+        _ => "OP_UNKNOWN",
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct TransactionInput {
-    pub previous_output: OutPoint,
-    pub script_sig: Script,
-    pub sequence: u32,
+/// A CScriptNum, without the minimal-encoding check: little-endian magnitude
+/// with the sign in the top bit of the last byte. Callers pass at most four
+/// bytes.
+fn script_num(data: &[u8]) -> i64 {
+    let Some(&last) = data.last() else {
+        return 0;
+    };
+    let magnitude = data
+        .iter()
+        .rev()
+        .fold(0i64, |acc, &b| (acc << 8) | b as i64)
+        & !(0x80 << (8 * (data.len() - 1)));
+    if last & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
 }
 
-impl TransactionInput {
-    pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
-        Self {
-            previous_output,
-            script_sig,
-            sequence,
-        }
+/// Core's `IsValidSignatureEncoding`: a strict DER signature followed by a
+/// hash type byte.
+fn is_strict_der_signature(sig: &[u8]) -> bool {
+    if !(9..=73).contains(&sig.len()) || sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = self.previous_output.to_bytes();
-        result.extend_from_slice(&self.script_sig.to_bytes());
-        result.extend_from_slice(&self.sequence.to_le_bytes());
-        result
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() || sig[2] != 0x02 || len_r == 0 {
+        return false;
+    }
+    if sig[4] & 0x80 != 0 || (len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0) {
+        return false;
+    }
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
     }
+    !(len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0)
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (prev_out, offset1) = OutPoint::from_bytes(bytes)?;
-        let (script, offset2) = Script::from_bytes(&bytes[offset1..])?;
-        if bytes.len() < offset1 + offset2 + 4 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let sequence = u32::from_le_bytes(
-            bytes[offset1 + offset2..offset1 + offset2 + 4]
-                .try_into()
-                .unwrap(),
-        );
-        Ok((Self::new(prev_out, script, sequence), offset1 + offset2 + 4))
+/// The `[...]` name Core appends to a signature push in a scriptSig, if
+/// `data` is a strict DER signature with a defined hash type.
+fn sighash_suffix(data: &[u8]) -> Option<&'static str> {
+    if !is_strict_der_signature(data) {
+        return None;
+    }
+    match *data.last()? as u32 {
+        SIGHASH_ALL => Some("ALL"),
+        SIGHASH_NONE => Some("NONE"),
+        SIGHASH_SINGLE => Some("SINGLE"),
+        0x81 => Some("ALL|ANYONECANPAY"),
+        0x82 => Some("NONE|ANYONECANPAY"),
+        0x83 => Some("SINGLE|ANYONECANPAY"),
+        _ => None,
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct BitcoinTransaction {
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Instruction {
+    PushBytes(Vec<u8>),
+    Op(u8),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScriptType {
+    P2PKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+    OpReturn,
+    NonStandard,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Script {
+    pub bytes: Vec<u8>,
+}
+
+impl Script {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Script { bytes }
+    }
+
+    /// Like `new`, but fails with `InvalidFormat` for scripts longer than
+    /// `max` bytes, e.g. `MAX_SCRIPT_SIZE`.
+    pub fn new_checked(bytes: Vec<u8>, max: usize) -> Result<Self, BitcoinError> {
+        if bytes.len() > max {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Script::new(bytes))
+    }
+
+    /// True for a zero-length script, such as the scriptSig of a native
+    /// SegWit input. It still encodes as a single 0x00 length byte.
+    pub fn is_empty_script(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        CompactSize::new(self.bytes.len() as u64).encoded_len() + self.bytes.len()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (script, consumed) = ScriptRef::from_bytes(bytes)?;
+        Ok((script.to_owned(), consumed))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let size = CompactSize::consensus_decode(reader)?;
+        if size.value > MAX_TRANSACTION_SIZE as u64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut data = Vec::new();
+        reader.take(size.value).read_to_end(&mut data)?;
+        if (data.len() as u64) < size.value {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        Ok(Script::new(data))
+    }
+
+    /// Unlike `from_hex` on the other types, this takes the bare script
+    /// bytes without a length prefix, as shown in RPC output.
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        let bytes = hex::decode(s).map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Script::new(bytes))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
+    }
+
+    /// Renders the script the way Bitcoin Core's `decodescript` does. Pushes
+    /// of up to four bytes and OP_0, OP_1NEGATE and OP_1 to OP_16 print as
+    /// decimal script numbers, longer pushes as hex, other opcodes by name. A
+    /// malformed push ends the output with `[error]`.
+    pub fn to_asm(&self) -> String {
+        self.asm(false)
+    }
+
+    /// With `decode_sighash`, a push that is a strictly DER-encoded signature
+    /// with a defined hash type prints as `<hex>[ALL]` and so on, as Core
+    /// does for scriptSigs in `decoderawtransaction`.
+    fn asm(&self, decode_sighash: bool) -> String {
+        let decode_sighash = decode_sighash
+            && self.bytes.first() != Some(&OP_RETURN)
+            && self.bytes.len() <= MAX_SCRIPT_SIZE;
+        let mut instructions = Vec::new();
+        let malformed = self.parse_instructions(&mut instructions).is_err();
+        let mut asm: Vec<String> = instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::PushBytes(data) if data.len() <= 4 => {
+                    format!("{}", script_num(data))
+                }
+                Instruction::PushBytes(data) => match sighash_suffix(data) {
+                    Some(name) if decode_sighash => {
+                        format!("{}[{}]", hex::encode(&data[..data.len() - 1]), name)
+                    }
+                    _ => hex::encode(data),
+                },
+                Instruction::Op(OP_0) => String::from("0"),
+                Instruction::Op(OP_1NEGATE) => String::from("-1"),
+                Instruction::Op(opcode @ 0x51..=0x60) => format!("{}", opcode - 0x50),
+                Instruction::Op(opcode) => String::from(opcode_name(*opcode)),
+            })
+            .collect();
+        if malformed {
+            asm.push(String::from("[error]"));
+        }
+        asm.join(" ")
+    }
+
+    pub fn instructions(&self) -> Result<Vec<Instruction>, BitcoinError> {
+        let mut result = Vec::new();
+        self.parse_instructions(&mut result)?;
+        Ok(result)
+    }
+
+    fn parse_instructions(&self, result: &mut Vec<Instruction>) -> Result<(), BitcoinError> {
+        let mut i = 0;
+        while i < self.bytes.len() {
+            let opcode = self.bytes[i];
+            i += 1;
+            let push_len = match opcode {
+                0x01..=0x4B => opcode as usize,
+                OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => {
+                    let width = match opcode {
+                        OP_PUSHDATA1 => 1,
+                        OP_PUSHDATA2 => 2,
+                        _ => 4,
+                    };
+                    let prefix = self
+                        .bytes
+                        .get(i..i + width)
+                        .ok_or(BitcoinError::InvalidFormat)?;
+                    i += width;
+                    prefix
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, &b| (acc << 8) | b as usize)
+                }
+                _ => {
+                    result.push(Instruction::Op(opcode));
+                    continue;
+                }
+            };
+            let data = self
+                .bytes
+                .get(i..i.saturating_add(push_len))
+                .ok_or(BitcoinError::InvalidFormat)?;
+            result.push(Instruction::PushBytes(data.to_vec()));
+            i += push_len;
+        }
+        Ok(())
+    }
+
+    /// Compares scripts after dropping trailing OP_NOP, OP_NOP1 and
+    /// OP_NOP4 to OP_NOP10 opcodes (0x61, 0xB0, 0xB3 to 0xB9). OP_NOP2 and
+    /// OP_NOP3 are not padding: they are OP_CHECKLOCKTIMEVERIFY and
+    /// OP_CHECKSEQUENCEVERIFY. Scripts that fail to parse compare byte for
+    /// byte.
+    pub fn is_equivalent(&self, other: &Script) -> bool {
+        self.without_nop_padding() == other.without_nop_padding()
+    }
+
+    fn without_nop_padding(&self) -> &[u8] {
+        let Ok(instructions) = self.instructions() else {
+            return &self.bytes;
+        };
+        // Opcodes are one byte each, so every trailing no-op trims one byte.
+        let padding = instructions
+            .iter()
+            .rev()
+            .take_while(|instruction| {
+                matches!(instruction, Instruction::Op(0x61 | 0xB0 | 0xB3..=0xB9))
+            })
+            .count();
+        &self.bytes[..self.bytes.len() - padding]
+    }
+
+    /// Splits a P2PKH scriptSig into its signature and public key pushes.
+    /// The signature must look like DER plus a sighash byte and the key
+    /// must be 33 (compressed) or 65 (uncompressed) bytes.
+    pub fn parse_p2pkh_sig(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let mut instructions = self.instructions().ok()?.into_iter();
+        let (Some(Instruction::PushBytes(signature)), Some(Instruction::PushBytes(pubkey)), None) = (
+            instructions.next(),
+            instructions.next(),
+            instructions.next(),
+        ) else {
+            return None;
+        };
+        let signature_ok = signature.len() >= 9 && signature[0] == 0x30;
+        let pubkey_ok = matches!(
+            (pubkey.first(), pubkey.len()),
+            (Some(0x02 | 0x03), 33) | (Some(0x04), 65)
+        );
+        (signature_ok && pubkey_ok).then_some((signature, pubkey))
+    }
+
+    /// Whether this is a P2PKH scriptPubKey paying to `hash160`.
+    pub fn pays_to_pubkey_hash(&self, hash160: &[u8; 20]) -> bool {
+        self.script_type() == ScriptType::P2PKH && self.bytes[3..23] == hash160[..]
+    }
+
+    /// HASH160 of the raw script bytes: the hash a P2SH output commits to
+    /// when this is its redeem script.
+    pub fn script_hash(&self) -> [u8; 20] {
+        hash160(&self.bytes)
+    }
+
+    /// The P2SH scriptPubKey `OP_HASH160 <script_hash> OP_EQUAL` that this
+    /// redeem script unlocks.
+    pub fn to_p2sh(&self) -> Script {
+        let mut bytes = Vec::with_capacity(23);
+        bytes.extend_from_slice(&[OP_HASH160, 20]);
+        bytes.extend_from_slice(&self.script_hash());
+        bytes.push(OP_EQUAL);
+        Script::new(bytes)
+    }
+
+    /// Whether this is a P2SH scriptPubKey paying to `hash160`.
+    pub fn pays_to_script_hash(&self, hash160: &[u8; 20]) -> bool {
+        self.script_type() == ScriptType::P2SH && self.bytes[2..22] == hash160[..]
+    }
+
+    /// The payload pushed right after a leading OP_RETURN. For scripts with
+    /// several pushes only the first is returned.
+    pub fn op_return_data(&self) -> Option<Vec<u8>> {
+        if self.bytes.first() != Some(&OP_RETURN) {
+            return None;
+        }
+        let mut instructions = Vec::new();
+        // A malformed tail does not invalidate a well-formed first push.
+        let _ = self.parse_instructions(&mut instructions);
+        match instructions.into_iter().nth(1) {
+            Some(Instruction::PushBytes(data)) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// The witness version of a BIP 141 witness program: a push opcode for
+    /// 0 through 16 followed by a single 2-to-40-byte push.
+    pub fn witness_version(&self) -> Option<u8> {
+        match self.bytes.as_slice() {
+            [version, len, program @ ..]
+                if (2..=40).contains(len) && program.len() == *len as usize =>
+            {
+                match *version {
+                    OP_0 => Some(0),
+                    0x51..=0x60 => Some(version - OP_1 + 1),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn witness_program(&self) -> Option<&[u8]> {
+        self.witness_version().map(|_| &self.bytes[2..])
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        match (self.witness_version(), self.witness_program()) {
+            (Some(0), Some(program)) if program.len() == 20 => return ScriptType::P2WPKH,
+            (Some(0), Some(program)) if program.len() == 32 => return ScriptType::P2WSH,
+            (Some(1), Some(program)) if program.len() == 32 => return ScriptType::P2TR,
+            _ => {}
+        }
+        match self.bytes.as_slice() {
+            [
+                OP_DUP,
+                OP_HASH160,
+                0x14,
+                hash @ ..,
+                OP_EQUALVERIFY,
+                OP_CHECKSIG,
+            ] if hash.len() == 20 => ScriptType::P2PKH,
+            [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => ScriptType::P2SH,
+            [OP_RETURN, ..] => ScriptType::OpReturn,
+            _ => ScriptType::NonStandard,
+        }
+    }
+
+    /// The address that pays to this scriptPubKey, or `None` for OP_RETURN and
+    /// non-standard scripts. Signet shares testnet's prefixes.
+    pub fn address(&self, network: Network) -> Option<String> {
+        let (pubkey_hash_version, script_hash_version, hrp) = match network {
+            Network::Mainnet => (0x00, 0x05, bech32::hrp::BC),
+            Network::Testnet | Network::Signet => (0x6F, 0xC4, bech32::hrp::TB),
+            Network::Regtest => (0x6F, 0xC4, bech32::hrp::BCRT),
+        };
+        let base58 = |version: u8, hash: &[u8]| {
+            let mut payload = Vec::with_capacity(1 + hash.len());
+            payload.push(version);
+            payload.extend_from_slice(hash);
+            base58::encode_check(&payload)
+        };
+        match self.script_type() {
+            ScriptType::P2PKH => Some(base58(pubkey_hash_version, &self.bytes[3..23])),
+            ScriptType::P2SH => Some(base58(script_hash_version, &self.bytes[2..22])),
+            ScriptType::P2WPKH | ScriptType::P2WSH | ScriptType::P2TR => {
+                let version = bech32::Fe32::try_from(self.witness_version()?).ok()?;
+                bech32::segwit::encode(hrp, version, self.witness_program()?).ok()
+            }
+            ScriptType::OpReturn | ScriptType::NonStandard => None,
+        }
+    }
+}
+
+impl Encode for Script {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        let len = CompactSize::new(self.bytes.len() as u64).consensus_encode(writer)?;
+        writer.write_all(&self.bytes)?;
+        Ok(len + self.bytes.len())
+    }
+}
+
+impl Deref for Script {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
+/// Renders the ASM form, or plain hex if the script does not parse.
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.instructions().is_ok() {
+            f.write_str(&self.to_asm())
+        } else {
+            f.write_str(&self.to_hex())
+        }
+    }
+}
+
+/// A script borrowed from the buffer it was decoded from, for scanning
+/// without copying every script.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptRef<'a>(pub &'a [u8]);
+
+impl<'a> ScriptRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        let (size, size_len) = CompactSize::from_bytes(bytes)?;
+        let total_len = size_len
+            .checked_add(size.to_usize()?)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        if bytes.len() < total_len {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        Ok((ScriptRef(&bytes[size_len..total_len]), total_len))
+    }
+
+    pub fn to_owned(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder {
+    bytes: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_opcode(mut self, opcode: u8) -> Self {
+        self.bytes.push(opcode);
+        self
+    }
+
+    /// Pushes `data` with the shortest push opcode for its length. Fails if
+    /// the data is too long for OP_PUSHDATA4.
+    pub fn push_slice(mut self, data: &[u8]) -> Result<Self, BitcoinError> {
+        let len = data.len();
+        match len {
+            0..=0x4B => self.bytes.push(len as u8),
+            0x4C..=0xFF => self.bytes.extend_from_slice(&[OP_PUSHDATA1, len as u8]),
+            0x100..=0xFFFF => {
+                self.bytes.push(OP_PUSHDATA2);
+                self.bytes.extend_from_slice(&(len as u16).to_le_bytes());
+            }
+            _ => {
+                let len = u32::try_from(len).map_err(|_| BitcoinError::InvalidFormat)?;
+                self.bytes.push(OP_PUSHDATA4);
+                self.bytes.extend_from_slice(&len.to_le_bytes());
+            }
+        }
+        self.bytes.extend_from_slice(data);
+        Ok(self)
+    }
+
+    /// Pushes a number using OP_0, OP_1NEGATE or OP_1..OP_16 where possible,
+    /// otherwise as a minimally encoded little-endian sign-magnitude push.
+    pub fn push_int(self, n: i64) -> Self {
+        match n {
+            0 => return self.push_opcode(OP_0),
+            -1 => return self.push_opcode(OP_1NEGATE),
+            1..=16 => return self.push_opcode(OP_1 + (n as u8 - 1)),
+            _ => {}
+        }
+        let mut magnitude = n.unsigned_abs();
+        let mut encoded = Vec::new();
+        while magnitude > 0 {
+            encoded.push(magnitude as u8);
+            magnitude >>= 8;
+        }
+        // The top bit of the last byte is the sign; add a byte if it is taken.
+        if encoded.last().is_some_and(|&b| b & 0x80 != 0) {
+            encoded.push(if n < 0 { 0x80 } else { 0x00 });
+        } else if n < 0 {
+            *encoded.last_mut().unwrap() |= 0x80;
+        }
+        self.push_slice(&encoded)
+            .expect("a number is at most 9 bytes")
+    }
+
+    pub fn build(self) -> Script {
+        Script::new(self.bytes)
+    }
+}
+
+/// A `Script` used as an input's unlocking script, and the type of
+/// `TransactionInput::script_sig`. A plain `Script` must be wrapped with
+/// `ScriptSig::new`, so an input's script cannot end up in an output:
+///
+/// ```compile_fail
+/// use rust_week_3_exercises::{BitcoinTransaction, TransactionOutput};
+///
+/// fn reuse(tx: BitcoinTransaction) -> TransactionOutput {
+///     let input = tx.inputs[0].clone();
+///     TransactionOutput::new(0, input.script_sig)
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct ScriptSig(pub Script);
+
+/// A `Script` used as an output's locking script, and the type of
+/// `TransactionOutput::script_pubkey`. The counterpart of `ScriptSig`, and
+/// likewise rejected where an input script is expected:
+///
+/// ```compile_fail
+/// use rust_week_3_exercises::{OutPoint, TransactionBuilder, TransactionOutput};
+///
+/// fn spend(output: TransactionOutput) -> TransactionBuilder {
+///     TransactionBuilder::new().add_input(OutPoint::new([0; 32], 0), output.script_pubkey, 0)
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct ScriptPubKey(pub Script);
+
+macro_rules! impl_script_newtype {
+    ($($ty:ident),*) => {$(
+        impl $ty {
+            pub fn new(script: Script) -> Self {
+                $ty(script)
+            }
+        }
+
+        impl From<$ty> for Script {
+            fn from(script: $ty) -> Self {
+                script.0
+            }
+        }
+
+        impl Deref for $ty {
+            type Target = Script;
+            fn deref(&self) -> &Script {
+                &self.0
+            }
+        }
+    )*};
+}
+
+impl_script_newtype!(ScriptSig, ScriptPubKey);
+
+const SEQUENCE_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+const SEQUENCE_GRANULARITY: u32 = 512;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    Seconds(u32),
+}
+
+/// An input's `nSequence`, which also carries the BIP 68 relative lock time.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
+pub struct Sequence(pub u32);
+
+impl Sequence {
+    pub fn final_sequence() -> Self {
+        Sequence(0xFFFFFFFF)
+    }
+
+    /// Signals BIP 125 replaceability without enabling a relative lock time.
+    pub fn enable_rbf() -> Self {
+        Sequence(0xFFFFFFFD)
+    }
+
+    pub fn from_height(height: u16) -> Self {
+        Sequence(height as u32)
+    }
+
+    /// Relative lock times count in 512-second units, so `seconds` is rounded
+    /// up to the next unit. Fails if that exceeds 65535 units.
+    pub fn from_seconds(seconds: u32) -> Result<Self, BitcoinError> {
+        let units = seconds.div_ceil(SEQUENCE_GRANULARITY);
+        if units > SEQUENCE_LOCKTIME_MASK {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Sequence(SEQUENCE_TYPE_FLAG | units))
+    }
+
+    pub fn is_relative_lock_time(&self) -> bool {
+        self.0 & SEQUENCE_DISABLE_FLAG == 0
+    }
+
+    pub fn to_relative_lock_time(&self) -> Option<RelativeLockTime> {
+        if !self.is_relative_lock_time() {
+            return None;
+        }
+        let value = (self.0 & SEQUENCE_LOCKTIME_MASK) as u16;
+        if self.0 & SEQUENCE_TYPE_FLAG != 0 {
+            Some(RelativeLockTime::Seconds(
+                value as u32 * SEQUENCE_GRANULARITY,
+            ))
+        } else {
+            Some(RelativeLockTime::Blocks(value))
+        }
+    }
+}
+
+impl From<u32> for Sequence {
+    fn from(value: u32) -> Self {
+        Sequence(value)
+    }
+}
+
+impl From<Sequence> for u32 {
+    fn from(sequence: Sequence) -> Self {
+        sequence.0
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TransactionInput {
+    pub previous_output: OutPoint,
+    pub script_sig: ScriptSig,
+    pub sequence: Sequence,
+}
+
+impl TransactionInput {
+    pub fn new(
+        previous_output: OutPoint,
+        script_sig: ScriptSig,
+        sequence: impl Into<Sequence>,
+    ) -> Self {
+        Self {
+            previous_output,
+            script_sig,
+            sequence: sequence.into(),
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        self.previous_output.serialized_len() + self.script_sig.serialized_len() + 4
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (input, consumed) = TransactionInputRef::from_bytes(bytes)?;
+        Ok((input.to_owned(), consumed))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let previous_output = OutPoint::consensus_decode(reader)?;
+        let script_sig = ScriptSig::new(Script::consensus_decode(reader)?);
+        let sequence = u32::from_le_bytes(read_array_from(reader)?);
+        Ok(Self::new(previous_output, script_sig, sequence))
+    }
+
+    /// BIP 125: any sequence below 0xFFFFFFFE opts in to replacement.
+    pub fn signals_rbf(&self) -> bool {
+        self.sequence.0 < 0xFFFFFFFE
+    }
+
+    pub fn with_script_sig(&self, script_sig: ScriptSig) -> TransactionInput {
+        Self {
+            script_sig,
+            ..self.clone()
+        }
+    }
+}
+
+impl Encode for TransactionInput {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        let mut len = self.previous_output.consensus_encode(writer)?;
+        len += self.script_sig.consensus_encode(writer)?;
+        writer.write_all(&self.sequence.0.to_le_bytes())?;
+        Ok(len + 4)
+    }
+}
+
+/// A transaction input whose scriptSig borrows from the buffer it was decoded
+/// from, so scanning inputs does not allocate per script.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionInputRef<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: ScriptRef<'a>,
+    pub sequence: Sequence,
+}
+
+impl<'a> TransactionInputRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        let (previous_output, outpoint_len) = OutPoint::from_bytes(bytes)?;
+        let (script_sig, script_len) = ScriptRef::from_bytes(&bytes[outpoint_len..])?;
+        let sequence_at = outpoint_len
+            .checked_add(script_len)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let rest = bytes
+            .get(sequence_at..)
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        let (sequence, sequence_len) = read_u32_le(rest)?;
+        let input = TransactionInputRef {
+            previous_output,
+            script_sig,
+            sequence: Sequence(sequence),
+        };
+        Ok((input, sequence_at + sequence_len))
+    }
+
+    pub fn to_owned(&self) -> TransactionInput {
+        TransactionInput::new(
+            self.previous_output.clone(),
+            ScriptSig::new(self.script_sig.to_owned()),
+            self.sequence,
+        )
+    }
+}
+
+pub const SATOSHIS_PER_BTC: u64 = 100_000_000;
+pub const MAX_MONEY: u64 = 21_000_000 * SATOSHIS_PER_BTC;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub fn from_sat(sat: u64) -> Self {
+        Amount(sat)
+    }
+
+    pub fn to_sat(&self) -> u64 {
+        self.0
+    }
+
+    pub fn from_btc(btc: f64) -> Result<Self, BitcoinError> {
+        if !btc.is_finite() || btc < 0.0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        // Round half up by hand; `f64::round` is unavailable without `std`.
+        let sat = btc * SATOSHIS_PER_BTC as f64 + 0.5;
+        if sat >= (MAX_MONEY + 1) as f64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Amount(sat as u64))
+    }
+
+    pub fn to_btc(&self) -> f64 {
+        self.0 as f64 / SATOSHIS_PER_BTC as f64
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0
+            .checked_add(other.0)
+            .filter(|&sat| sat <= MAX_MONEY)
+            .map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:08} BTC",
+            self.0 / SATOSHIS_PER_BTC,
+            self.0 % SATOSHIS_PER_BTC
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: ScriptPubKey,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: ScriptPubKey) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        8 + self.script_pubkey.serialized_len()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (value, value_len) = read_u64_le(bytes)?;
+        let (script_pubkey, consumed) = Script::from_bytes(&bytes[value_len..])?;
+        Ok((
+            Self::new(value, ScriptPubKey::new(script_pubkey)),
+            value_len + consumed,
+        ))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let value = u64::from_le_bytes(read_array_from(reader)?);
+        let script_pubkey = Script::consensus_decode(reader)?;
+        Ok(Self::new(value, ScriptPubKey::new(script_pubkey)))
+    }
+
+    pub fn amount(&self) -> Amount {
+        Amount::from_sat(self.value)
+    }
+
+    /// Bitcoin Core's `IsDust`: the output is worth less than the fee, at
+    /// `dust_relay_fee_per_kb` sat/kvB, of creating and later spending it.
+    /// Spending a witness program is assumed to cost 67 bytes, anything else
+    /// 148. Like Core's `CFeeRate::GetFee`, the fee rounds up, so any
+    /// non-zero rate costs at least 1 sat. Provably unspendable outputs are
+    /// never dust.
+    pub fn is_dust(&self, dust_relay_fee_per_kb: u64) -> bool {
+        let script = &self.script_pubkey;
+        if script.first() == Some(&OP_RETURN) || script.len() > MAX_SCRIPT_SIZE {
+            return false;
+        }
+        let spend_size = if script.witness_version().is_some() {
+            32 + 4 + 1 + 107 / 4 + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        let size = (self.serialized_len() + spend_size) as u64;
+        self.value < size.saturating_mul(dust_relay_fee_per_kb).div_ceil(1000)
+    }
+}
+
+impl Encode for TransactionOutput {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        writer.write_all(&self.value.to_le_bytes())?;
+        Ok(8 + self.script_pubkey.consensus_encode(writer)?)
+    }
+}
+
+/// The witness stack of one input: a list of byte-string elements, encoded
+/// as a CompactSize element count followed by each length-prefixed element.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
+pub struct Witness {
+    elements: Vec<Vec<u8>>,
+}
+
+impl Witness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, element: &[u8]) {
+        self.elements.push(element.to_vec());
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.elements.get(index).map(Vec::as_slice)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.elements.iter().map(Vec::as_slice)
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        let count_len = |count: usize| CompactSize::new(count as u64).encoded_len();
+        count_len(self.len())
+            + self
+                .iter()
+                .map(|element| count_len(element.len()) + element.len())
+                .sum::<usize>()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (elements, consumed) = decode_vec(bytes, |bytes| {
+            ScriptRef::from_bytes(bytes).map(|(element, len)| (element.0.to_vec(), len))
+        })?;
+        Ok((Self { elements }, consumed))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::consensus_decode_limited(reader, usize::MAX)
+    }
+
+    #[cfg(feature = "std")]
+    fn consensus_decode_limited<R: Read>(
+        reader: &mut R,
+        max_items: usize,
+    ) -> Result<Self, BitcoinError> {
+        let count = stream_count(&CompactSize::consensus_decode(reader)?, 1, max_items)?;
+        let mut witness = Self::new();
+        for _ in 0..count {
+            witness
+                .elements
+                .push(Script::consensus_decode(reader)?.bytes);
+        }
+        Ok(witness)
+    }
+}
+
+impl From<Vec<Vec<u8>>> for Witness {
+    fn from(elements: Vec<Vec<u8>>) -> Self {
+        Self { elements }
+    }
+}
+
+impl Encode for Witness {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        let mut len = CompactSize::new(self.len() as u64).consensus_encode(writer)?;
+        for element in self.iter() {
+            len += CompactSize::new(element.len() as u64).consensus_encode(writer)?;
+            writer.write_all(element)?;
+            len += element.len();
+        }
+        Ok(len)
+    }
+}
+
+/// Bitcoin Core's default `-dustrelayfee`, in sat/kvB.
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+
+/// Blocks that must follow a coinbase before its outputs can be spent.
+pub const COINBASE_MATURITY: u32 = 100;
+
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// Upper bound on a serialized transaction: one filling a whole block of
+/// `MAX_BLOCK_WEIGHT` (4,000,000) with witness data.
+pub const MAX_TRANSACTION_SIZE: usize = 4_000_000;
+
+/// Bitcoin Core's `MAX_STANDARD_TX_WEIGHT` relay policy limit.
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+/// Bitcoin Core's `MIN_STANDARD_TX_NONWITNESS_SIZE` relay policy limit,
+/// checked against the stripped size.
+pub const MIN_STANDARD_TX_NONWITNESS_SIZE: usize = 65;
+
+/// Caps applied while decoding untrusted transactions. The defaults are the
+/// most a transaction of `MAX_TRANSACTION_SIZE` bytes can hold, so they accept
+/// everything `from_bytes` does; tighten them to harden a parser.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DecodeLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    /// Applies to scriptSigs and scriptPubKeys, not witness items.
+    pub max_script_len: usize,
+    /// Per input.
+    pub max_witness_items: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_inputs: MAX_TRANSACTION_SIZE / MIN_INPUT_SIZE,
+            max_outputs: MAX_TRANSACTION_SIZE / MIN_OUTPUT_SIZE,
+            max_script_len: MAX_TRANSACTION_SIZE,
+            max_witness_items: MAX_TRANSACTION_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LockTimeKind {
+    Disabled,
+    BlockHeight(u32),
+    Timestamp(u32),
+}
+
+/// A `len`-byte region starting at `offset` in a decoded buffer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ByteSpan {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl ByteSpan {
+    pub fn range(&self) -> core::ops::Range<usize> {
+        self.offset..self.offset + self.len
+    }
+}
+
+/// Where each input's scriptSig and each output's scriptPubKey sit in the
+/// buffer passed to `BitcoinTransaction::from_bytes_with_spans`. The spans
+/// cover the script bytes only, not their length prefixes.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct TxSpans {
+    pub script_sigs: Vec<ByteSpan>,
+    pub script_pubkeys: Vec<ByteSpan>,
+}
+
+fn bip69_input_key(input: &TransactionInput) -> ([u8; 32], u32) {
+    let mut txid = input.previous_output.txid.0;
+    txid.reverse();
+    (txid, input.previous_output.vout)
+}
+
+fn bip69_output_cmp(a: &TransactionOutput, b: &TransactionOutput) -> core::cmp::Ordering {
+    a.value
+        .cmp(&b.value)
+        .then_with(|| a.script_pubkey.bytes.cmp(&b.script_pubkey.bytes))
+}
+
+/// The fields `BitcoinTransaction::from_bytes_partial` managed to decode.
+/// Fields after the failure point are `None` or hold only the items read.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PartialTransaction {
+    pub version: Option<u32>,
+    pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
+    pub witnesses: Vec<Witness>,
+    pub lock_time: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
+    /// Witness stack for each input, in input order. Empty for legacy
+    /// transactions; a missing trailing entry is treated as an empty stack.
+    #[serde(default)]
+    pub witnesses: Vec<Witness>,
 }
 
-impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
-        Self {
-            version,
-            inputs,
-            lock_time,
+impl BitcoinTransaction {
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        lock_time: u32,
+    ) -> Self {
+        Self {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+            witnesses: Vec::new(),
+        }
+    }
+
+    /// Preferred over reading the `inputs` field directly, which may stop
+    /// being public.
+    pub fn inputs(&self) -> &[TransactionInput] {
+        &self.inputs
+    }
+
+    /// Preferred over reading the `outputs` field directly, which may stop
+    /// being public.
+    pub fn outputs(&self) -> &[TransactionOutput] {
+        &self.outputs
+    }
+
+    /// The coins this transaction creates, keyed by the outpoint that will
+    /// spend each one.
+    pub fn created_utxos(&self) -> Vec<(OutPoint, &TransactionOutput)> {
+        let txid = self.txid();
+        self.outputs
+            .iter()
+            .enumerate()
+            .map(|(vout, output)| (OutPoint::new(txid.0, vout as u32), output))
+            .collect()
+    }
+
+    /// Index of the input spending `outpoint`, if any.
+    pub fn spends(&self, outpoint: &OutPoint) -> Option<usize> {
+        self.inputs
+            .iter()
+            .position(|input| input.previous_output == *outpoint)
+    }
+
+    /// Sorts inputs by previous txid in display order, then vout, and
+    /// outputs by value, then scriptPubKey bytes, as BIP 69 specifies.
+    /// Witnesses move with their inputs.
+    pub fn sort_bip69(&mut self) {
+        if self.witnesses.is_empty() {
+            self.inputs.sort_by_key(bip69_input_key);
+        } else {
+            self.witnesses.resize(self.inputs.len(), Witness::new());
+            let mut pairs: Vec<(TransactionInput, Witness)> = self
+                .inputs
+                .drain(..)
+                .zip(self.witnesses.drain(..))
+                .collect();
+            pairs.sort_by_key(|(input, _)| bip69_input_key(input));
+            (self.inputs, self.witnesses) = pairs.into_iter().unzip();
+        }
+        self.outputs.sort_by(bip69_output_cmp);
+    }
+
+    pub fn is_bip69_sorted(&self) -> bool {
+        self.inputs
+            .windows(2)
+            .all(|pair| bip69_input_key(&pair[0]) <= bip69_input_key(&pair[1]))
+            && self
+                .outputs
+                .windows(2)
+                .all(|pair| bip69_output_cmp(&pair[0], &pair[1]).is_le())
+    }
+
+    /// The outpoints spent by this transaction, in input order.
+    pub fn iter_outpoints(&self) -> impl Iterator<Item = &OutPoint> {
+        self.inputs.iter().map(|input| &input.previous_output)
+    }
+
+    /// True when any input carries a non-empty witness. Such a transaction
+    /// encodes in SegWit form; otherwise `to_bytes` matches `to_bytes_legacy`.
+    pub fn is_segwit(&self) -> bool {
+        self.witnesses.iter().any(|stack| !stack.is_empty())
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        self.len_with(self.is_segwit())
+    }
+
+    fn len_with(&self, include_witness: bool) -> usize {
+        let count_len = |count: usize| CompactSize::new(count as u64).encoded_len();
+        let mut len = 4 + count_len(self.inputs.len()) + count_len(self.outputs.len()) + 4;
+        len += self
+            .inputs
+            .iter()
+            .map(TransactionInput::serialized_len)
+            .sum::<usize>();
+        len += self
+            .outputs
+            .iter()
+            .map(TransactionOutput::serialized_len)
+            .sum::<usize>();
+        if include_witness {
+            len += 2;
+            for i in 0..self.inputs.len() {
+                len += self.witnesses.get(i).map_or(1, Witness::serialized_len);
+            }
+        }
+        len
+    }
+
+    fn encode_with<W: Write>(
+        &self,
+        writer: &mut W,
+        include_witness: bool,
+    ) -> Result<usize, BitcoinError> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        let mut len = 4;
+        if include_witness {
+            writer.write_all(&[0x00, 0x01])?;
+            len += 2;
+        }
+        len += CompactSize::new(self.inputs.len() as u64).consensus_encode(writer)?;
+        for input in &self.inputs {
+            len += input.consensus_encode(writer)?;
+        }
+        len += CompactSize::new(self.outputs.len() as u64).consensus_encode(writer)?;
+        for output in &self.outputs {
+            len += output.consensus_encode(writer)?;
+        }
+        if include_witness {
+            for i in 0..self.inputs.len() {
+                len += match self.witnesses.get(i) {
+                    Some(witness) => witness.consensus_encode(writer)?,
+                    None => Witness::new().consensus_encode(writer)?,
+                };
+            }
+        }
+        writer.write_all(&self.lock_time.to_le_bytes())?;
+        Ok(len + 4)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_limited(bytes, MAX_TRANSACTION_SIZE)
+    }
+
+    /// Like `from_bytes`, but only looks at the first `max_len` bytes.
+    /// Counts or lengths that would reach past them fail with `InvalidFormat`
+    /// before anything is allocated for them.
+    pub fn from_bytes_limited(bytes: &[u8], max_len: usize) -> Result<(Self, usize), BitcoinError> {
+        Self::decode_windowed(bytes, max_len, &DecodeLimits::default(), None)
+    }
+
+    /// Like `from_bytes`, but fails with `InvalidFormat` as soon as a count
+    /// or script length exceeds `limits`.
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: &DecodeLimits,
+    ) -> Result<(Self, usize), BitcoinError> {
+        Self::decode_windowed(bytes, MAX_TRANSACTION_SIZE, limits, None)
+    }
+
+    fn decode_windowed(
+        bytes: &[u8],
+        max_len: usize,
+        limits: &DecodeLimits,
+        spans: Option<&mut TxSpans>,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let window = &bytes[..bytes.len().min(max_len)];
+        match Self::decode_detailed(window, limits, spans) {
+            Ok(decoded) => Ok(decoded),
+            Err(err)
+                if window.len() < bytes.len() && err.error == BitcoinError::InsufficientBytes =>
+            {
+                Err(BitcoinError::InvalidFormat)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Like `from_bytes`, but fails if anything follows the transaction.
+    pub fn from_bytes_exact(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        Self::try_from(bytes)
+    }
+
+    /// Whether `bytes` is exactly one transaction in canonical form, i.e. it
+    /// decodes with nothing left over and re-encodes to the same bytes.
+    /// Non-minimal length prefixes and SegWit encodings with only empty
+    /// witnesses decode fine but fail this check.
+    pub fn round_trips(bytes: &[u8]) -> bool {
+        Self::from_bytes_exact(bytes).is_ok_and(|tx| tx.to_bytes() == bytes)
+    }
+
+    /// Like `from_bytes`, but also returns where each script lies in `bytes`.
+    pub fn from_bytes_with_spans(bytes: &[u8]) -> Result<(Self, TxSpans), BitcoinError> {
+        let mut spans = TxSpans::default();
+        let (tx, _) = Self::decode_windowed(
+            bytes,
+            MAX_TRANSACTION_SIZE,
+            &DecodeLimits::default(),
+            Some(&mut spans),
+        )?;
+        Ok((tx, spans))
+    }
+
+    /// Like `from_bytes`, but reports the byte offset and field at which
+    /// decoding failed.
+    pub fn from_bytes_detailed(bytes: &[u8]) -> Result<(Self, usize), DecodeError> {
+        Self::decode_detailed(bytes, &DecodeLimits::default(), None)
+    }
+
+    fn decode_detailed(
+        bytes: &[u8],
+        limits: &DecodeLimits,
+        spans: Option<&mut TxSpans>,
+    ) -> Result<(Self, usize), DecodeError> {
+        let mut partial = PartialTransaction::default();
+        let consumed = Self::decode_into(bytes, limits, &mut partial, spans)?;
+        let mut tx = Self::new(
+            partial.version.unwrap_or_default(),
+            partial.inputs,
+            partial.outputs,
+            partial.lock_time.unwrap_or_default(),
+        );
+        tx.witnesses = partial.witnesses;
+        Ok((tx, consumed))
+    }
+
+    /// Decodes as much as possible, returning the fields read before the
+    /// first error along with that error. Meant for inspecting malformed
+    /// data; use `from_bytes` to actually decode.
+    pub fn from_bytes_partial(bytes: &[u8]) -> (PartialTransaction, Option<BitcoinError>) {
+        let mut partial = PartialTransaction::default();
+        let error = Self::decode_into(bytes, &DecodeLimits::default(), &mut partial, None).err();
+        (partial, error.map(BitcoinError::from))
+    }
+
+    fn decode_into(
+        bytes: &[u8],
+        limits: &DecodeLimits,
+        partial: &mut PartialTransaction,
+        mut spans: Option<&mut TxSpans>,
+    ) -> Result<usize, DecodeError> {
+        let (version, mut offset) =
+            read_u32_le(bytes).map_err(|error| DecodeError::new(0, "version", error))?;
+        partial.version = Some(version);
+
+        // A 0x00 here is always the SegWit marker, never an empty input count.
+        let segwit = bytes.get(4) == Some(&0x00);
+        if segwit {
+            match bytes.get(5) {
+                Some(0x01) => offset += 2,
+                Some(_) => {
+                    return Err(DecodeError::new(
+                        5,
+                        "segwit flag",
+                        BitcoinError::InvalidFormat,
+                    ));
+                }
+                None => {
+                    return Err(DecodeError::new(
+                        5,
+                        "segwit flag",
+                        BitcoinError::InsufficientBytes,
+                    ));
+                }
+            }
+        }
+
+        let relative_to = |base| {
+            move |err: DecodeError| DecodeError::new(base + err.offset, err.field, err.error)
+        };
+
+        // Each script ends where the item does, less the 4-byte sequence for
+        // inputs, so its span follows from the lengths already decoded.
+        let base = offset;
+        offset += decode_vec_into(
+            &bytes[offset..],
+            MIN_INPUT_SIZE,
+            limits.max_inputs,
+            ("input count", "input"),
+            &mut partial.inputs,
+            |bytes, at| {
+                let (input, len) = TransactionInputRef::from_bytes(bytes)?;
+                let script_len = input.script_sig.0.len();
+                if script_len > limits.max_script_len {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.script_sigs.push(ByteSpan {
+                        offset: base + at + len - 4 - script_len,
+                        len: script_len,
+                    });
+                }
+                Ok((input.to_owned(), len))
+            },
+        )
+        .map_err(relative_to(offset))?;
+
+        let base = offset;
+        offset += decode_vec_into(
+            &bytes[offset..],
+            MIN_OUTPUT_SIZE,
+            limits.max_outputs,
+            ("output count", "output"),
+            &mut partial.outputs,
+            |bytes, at| {
+                let (output, len) = TransactionOutput::from_bytes(bytes)?;
+                let script_len = output.script_pubkey.len();
+                if script_len > limits.max_script_len {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                if let Some(spans) = spans.as_deref_mut() {
+                    spans.script_pubkeys.push(ByteSpan {
+                        offset: base + at + len - script_len,
+                        len: script_len,
+                    });
+                }
+                Ok((output, len))
+            },
+        )
+        .map_err(relative_to(offset))?;
+
+        if segwit {
+            for _ in 0..partial.inputs.len() {
+                let mut items = Vec::new();
+                offset += decode_vec_into(
+                    &bytes[offset..],
+                    1,
+                    limits.max_witness_items,
+                    ("witness item count", "witness item"),
+                    &mut items,
+                    |bytes, _| {
+                        ScriptRef::from_bytes(bytes).map(|(item, len)| (item.0.to_vec(), len))
+                    },
+                )
+                .map_err(relative_to(offset))?;
+                partial.witnesses.push(Witness::from(items));
+            }
+        }
+
+        let (lock_time, lock_time_len) = read_u32_le(&bytes[offset..])
+            .map_err(|error| DecodeError::new(offset, "lock_time", error))?;
+        partial.lock_time = Some(lock_time);
+        Ok(offset + lock_time_len)
+    }
+
+    /// Decodes back-to-back transactions until `bytes` is used up, such as the
+    /// body of a block after its header and transaction count.
+    pub fn parse_all(bytes: &[u8]) -> Result<Vec<Self>, BitcoinError> {
+        let mut txs = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (tx, consumed) =
+                Self::from_bytes(&bytes[offset..]).map_err(|_| BitcoinError::InvalidFormat)?;
+            txs.push(tx);
+            offset += consumed;
+        }
+        Ok(txs)
+    }
+
+    /// Reads at most `MAX_TRANSACTION_SIZE` bytes; a transaction that would
+    /// need more fails with `InvalidFormat`.
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Self::consensus_decode_with_limits(reader, &DecodeLimits::default())
+    }
+
+    /// `consensus_decode` with the caps of `from_bytes_with_limits`.
+    #[cfg(feature = "std")]
+    pub fn consensus_decode_with_limits<R: Read>(
+        reader: &mut R,
+        limits: &DecodeLimits,
+    ) -> Result<Self, BitcoinError> {
+        decode_with_budget(reader, |reader| Self::decode_from_reader(reader, limits))
+    }
+
+    #[cfg(feature = "std")]
+    fn decode_from_reader<R: Read>(
+        reader: &mut R,
+        limits: &DecodeLimits,
+    ) -> Result<Self, BitcoinError> {
+        let version = u32::from_le_bytes(read_array_from(reader)?);
+
+        // A 0x00 here is always the SegWit marker, never an empty input count.
+        let [first] = read_array_from(reader)?;
+        let segwit = first == 0x00;
+        let input_count = if segwit {
+            let [flag] = read_array_from(reader)?;
+            if flag != 0x01 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            CompactSize::consensus_decode(reader)?
+        } else {
+            CompactSize::decode_after_prefix(first, reader)?
+        };
+
+        let input_count = stream_count(&input_count, MIN_INPUT_SIZE, limits.max_inputs)?;
+        let mut inputs = Vec::new();
+        for _ in 0..input_count {
+            let input = TransactionInput::consensus_decode(reader)?;
+            if input.script_sig.len() > limits.max_script_len {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            inputs.push(input);
+        }
+
+        let output_count = CompactSize::consensus_decode(reader)?;
+        let output_count = stream_count(&output_count, MIN_OUTPUT_SIZE, limits.max_outputs)?;
+        let mut outputs = Vec::new();
+        for _ in 0..output_count {
+            let output = TransactionOutput::consensus_decode(reader)?;
+            if output.script_pubkey.len() > limits.max_script_len {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            outputs.push(output);
+        }
+
+        let mut witnesses = Vec::new();
+        if segwit {
+            for _ in 0..inputs.len() {
+                witnesses.push(Witness::consensus_decode_limited(
+                    reader,
+                    limits.max_witness_items,
+                )?);
+            }
+        }
+
+        let lock_time = u32::from_le_bytes(read_array_from(reader)?);
+
+        let mut tx = Self::new(version, inputs, outputs, lock_time);
+        tx.witnesses = witnesses;
+        Ok(tx)
+    }
+
+    /// Returns the double-SHA256 of the serialized transaction in internal
+    /// (little-endian) byte order. Block explorers show these bytes reversed.
+    /// Witness data is never part of the hashed serialization.
+    pub fn txid(&self) -> Txid {
+        Txid(sha256d(&self.to_bytes_legacy()))
+    }
+
+    /// The pre-SegWit serialization, without marker, flag or witnesses. This
+    /// is what `txid` hashes.
+    pub fn to_bytes_legacy(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_with(&mut bytes, false)
+            .expect("writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Writes the encoding into the front of `buf` without allocating and
+    /// returns its length. Fails with `InsufficientBytes`, leaving `buf`
+    /// untouched, if it is shorter than `serialized_len`.
+    pub fn encode_to_slice(&self, buf: &mut [u8]) -> Result<usize, BitcoinError> {
+        let len = self.serialized_len();
+        let mut out = buf.get_mut(..len).ok_or(BitcoinError::InsufficientBytes)?;
+        self.consensus_encode(&mut out)
+    }
+
+    /// A copy with every witness removed, so it encodes in legacy form.
+    pub fn strip_witness(&self) -> BitcoinTransaction {
+        BitcoinTransaction {
+            witnesses: Vec::new(),
+            ..self.clone()
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = self.version.to_le_bytes().to_vec();
-        result.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
+    /// Every input's outpoint, concatenated. BIP 143 hashes this as
+    /// `hashPrevouts`.
+    pub fn serialize_prevouts(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.inputs.len() * 36);
         for input in &self.inputs {
-            result.extend_from_slice(&input.to_bytes());
+            bytes.extend_from_slice(&input.previous_output.to_bytes());
         }
-        result.extend_from_slice(&self.lock_time.to_le_bytes());
-        result
+        bytes
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    /// Every input's sequence, concatenated. BIP 143 hashes this as
+    /// `hashSequence`.
+    pub fn serialize_sequences(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.inputs.len() * 4);
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.sequence.0.to_le_bytes());
         }
+        bytes
+    }
 
-        let version = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
-        let (size, offset1) = CompactSize::from_bytes(&bytes[4..])?;
-        let mut inputs = Vec::new();
-        let mut offset = 4 + offset1;
+    /// Every output, concatenated. BIP 143 hashes this as `hashOutputs`.
+    pub fn serialize_outputs(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for output in &self.outputs {
+            bytes.extend_from_slice(&output.to_bytes());
+        }
+        bytes
+    }
 
-        for _ in 0..size.value {
-            let (input, consumed) = TransactionInput::from_bytes(&bytes[offset..])?;
-            inputs.push(input);
-            offset += consumed;
+    /// The shape Bitcoin Core's `decoderawtransaction` RPC returns, limited to
+    /// the fields this crate can compute. Hashes are in display order, and
+    /// scriptSig `asm` marks signature hash types like `[ALL]` as Core does.
+    pub fn to_rpc_json(&self) -> serde_json::Value {
+        use serde_json::json;
+
+        let vin: Vec<serde_json::Value> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let mut entry = if self.is_coinbase() {
+                    json!({ "coinbase": input.script_sig.to_hex() })
+                } else {
+                    json!({
+                        "txid": input.previous_output.txid.to_display_string(),
+                        "vout": input.previous_output.vout,
+                        "scriptSig": {
+                            "asm": input.script_sig.asm(true),
+                            "hex": input.script_sig.to_hex(),
+                        },
+                    })
+                };
+                if let Some(stack) = self.witnesses.get(i).filter(|stack| !stack.is_empty()) {
+                    let items: Vec<String> = stack.iter().map(hex::encode).collect();
+                    entry["txinwitness"] = json!(items);
+                }
+                entry["sequence"] = json!(input.sequence.0);
+                entry
+            })
+            .collect();
+
+        let vout: Vec<serde_json::Value> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(n, output)| {
+                json!({
+                    "value": output.amount().to_btc(),
+                    "n": n,
+                    "scriptPubKey": {
+                        "asm": output.script_pubkey.to_asm(),
+                        "hex": output.script_pubkey.to_hex(),
+                    },
+                })
+            })
+            .collect();
+
+        json!({
+            "txid": self.txid().to_display_string(),
+            "hash": self.wtxid().to_display_string(),
+            "version": self.version_i32(),
+            "size": self.serialized_len(),
+            "vsize": self.vsize(),
+            "weight": self.weight(),
+            "locktime": self.lock_time,
+            "vin": vin,
+            "vout": vout,
+        })
+    }
+
+    /// Compares everything except witness data, which is exactly what the
+    /// txid commits to.
+    pub fn same_txid(&self, other: &BitcoinTransaction) -> bool {
+        self.version == other.version
+            && self.inputs == other.inputs
+            && self.outputs == other.outputs
+            && self.lock_time == other.lock_time
+    }
+
+    /// A copy of the transaction with input `index`'s scriptSig replaced,
+    /// or `InvalidFormat` if there is no such input.
+    pub fn with_input_script(
+        &self,
+        index: usize,
+        script_sig: ScriptSig,
+    ) -> Result<BitcoinTransaction, BitcoinError> {
+        let input = self.inputs.get(index).ok_or(BitcoinError::InvalidFormat)?;
+        let mut tx = self.clone();
+        tx.inputs[index] = input.with_script_sig(script_sig);
+        Ok(tx)
+    }
+
+    /// Replaces the sequence of input `index`, e.g. to signal RBF. Fails with
+    /// `InvalidFormat` if there is no such input.
+    pub fn set_sequence(&mut self, index: usize, seq: u32) -> Result<(), BitcoinError> {
+        let input = self
+            .inputs
+            .get_mut(index)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        input.sequence = Sequence(seq);
+        Ok(())
+    }
+
+    pub fn set_locktime(&mut self, lock_time: u32) {
+        self.lock_time = lock_time;
+    }
+
+    /// Double-SHA256 of the full serialization, marker, flag and witnesses
+    /// included. Equal to `txid` when the transaction carries no witness.
+    pub fn wtxid(&self) -> Txid {
+        Txid(sha256d(&self.to_bytes()))
+    }
+
+    /// A coinbase's single input spends the null outpoint. Its `script_sig`
+    /// carries arbitrary miner data and should not be disassembled as a script.
+    pub fn is_coinbase(&self) -> bool {
+        match self.inputs.as_slice() {
+            [input] => input.previous_output.is_null(),
+            _ => false,
         }
+    }
 
-        if bytes.len() < offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    /// The version as consensus code reads it: a signed `int32_t` stored in
+    /// the same four bytes as `version`.
+    pub fn version_i32(&self) -> i32 {
+        self.version as i32
+    }
+
+    /// BIP 68 relative lock times only apply from version 2 upwards; negative
+    /// versions do not enable them.
+    pub fn bip68_enabled(&self) -> bool {
+        self.version_i32() >= 2
+    }
+
+    /// One line for logs, e.g. `txid=f4184fc5 v=1 in=1 out=2 locktime=0 rbf=false`.
+    /// The txid is cut to its first 8 display-order hex characters.
+    pub fn summary(&self) -> String {
+        format!(
+            "txid={} v={} in={} out={} locktime={} rbf={}",
+            &self.txid().to_display_string()[..8],
+            self.version_i32(),
+            self.inputs.len(),
+            self.outputs.len(),
+            self.lock_time,
+            self.is_rbf()
+        )
+    }
+
+    /// First block height that may spend this coinbase's outputs, given the
+    /// height it was mined at. `None` for non-coinbase transactions.
+    pub fn coinbase_spendable_height(&self, mined_at: u32) -> Option<u32> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        mined_at.checked_add(COINBASE_MATURITY)
+    }
+
+    pub fn locktime_kind(&self) -> LockTimeKind {
+        match self.lock_time {
+            0 => LockTimeKind::Disabled,
+            height if height < LOCKTIME_THRESHOLD => LockTimeKind::BlockHeight(height),
+            time => LockTimeKind::Timestamp(time),
+        }
+    }
+
+    /// Mirrors Bitcoin Core's `IsFinalTx`: the lock time has passed for the
+    /// given block height and time, or every input opted out with a final
+    /// sequence number.
+    pub fn is_final(&self, height: u32, time: u32) -> bool {
+        let unlocked = match self.locktime_kind() {
+            LockTimeKind::Disabled => true,
+            LockTimeKind::BlockHeight(lock) => lock < height,
+            LockTimeKind::Timestamp(lock) => lock < time,
+        };
+        unlocked
+            || self
+                .inputs
+                .iter()
+                .all(|input| input.sequence == Sequence::final_sequence())
+    }
+
+    pub fn is_rbf(&self) -> bool {
+        self.inputs.iter().any(TransactionInput::signals_rbf)
+    }
+
+    /// Size of the serialization without marker, flag, or witness data.
+    pub fn stripped_size(&self) -> usize {
+        self.len_with(false)
+    }
+
+    /// BIP 141 weight: base size × 4 plus witness size × 1.
+    pub fn weight(&self) -> usize {
+        self.stripped_size() * 3 + self.serialized_len()
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    /// Whether Core's relay policy would accept this transaction's size.
+    pub fn is_standard_size(&self) -> bool {
+        self.weight() <= MAX_STANDARD_TX_WEIGHT
+            && self.stripped_size() >= MIN_STANDARD_TX_NONWITNESS_SIZE
+    }
+
+    pub fn total_output_value(&self) -> Result<u64, BitcoinError> {
+        self.outputs.iter().try_fold(0u64, |total, output| {
+            total
+                .checked_add(output.value)
+                .ok_or(BitcoinError::InvalidFormat)
+        })
+    }
+
+    pub fn fee(&self, prevout_values: &[u64]) -> Result<u64, BitcoinError> {
+        if prevout_values.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let total_in = prevout_values.iter().try_fold(0u64, |total, &value| {
+            total.checked_add(value).ok_or(BitcoinError::InvalidFormat)
+        })?;
+        total_in
+            .checked_sub(self.total_output_value()?)
+            .ok_or(BitcoinError::InvalidFormat)
+    }
+
+    /// Fee in satoshis per virtual byte. Same errors as `fee`, plus
+    /// `InvalidFormat` for a zero vsize.
+    pub fn fee_rate(&self, prevout_values: &[u64]) -> Result<f64, BitcoinError> {
+        let fee = self.fee(prevout_values)?;
+        match self.vsize() {
+            0 => Err(BitcoinError::InvalidFormat),
+            vsize => Ok(fee as f64 / vsize as f64),
         }
+    }
+}
+
+/// Generates only transactions that survive an encode/decode round trip: at
+/// least one input, and either no witnesses or one stack per input with at
+/// least one of them non-empty.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for BitcoinTransaction {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut inputs: Vec<TransactionInput> = u.arbitrary()?;
+        if inputs.is_empty() {
+            inputs.push(u.arbitrary()?);
+        }
+        let mut witnesses = Vec::new();
+        if u.arbitrary()? {
+            for _ in 0..inputs.len() {
+                witnesses.push(u.arbitrary::<Witness>()?);
+            }
+            if witnesses.iter().all(Witness::is_empty) {
+                witnesses[0].push(u.arbitrary()?);
+            }
+        }
+        Ok(Self {
+            version: u.arbitrary()?,
+            inputs,
+            outputs: u.arbitrary()?,
+            lock_time: u.arbitrary()?,
+            witnesses,
+        })
+    }
+}
+
+impl Encode for BitcoinTransaction {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        self.encode_with(writer, self.is_segwit())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    version: u32,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    lock_time: u32,
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            lock_time: 0,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn add_input(
+        mut self,
+        previous_output: OutPoint,
+        script_sig: ScriptSig,
+        sequence: impl Into<Sequence>,
+    ) -> Self {
+        self.inputs
+            .push(TransactionInput::new(previous_output, script_sig, sequence));
+        self
+    }
+
+    pub fn add_output(mut self, value: u64, script_pubkey: ScriptPubKey) -> Self {
+        self.outputs
+            .push(TransactionOutput::new(value, script_pubkey));
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
 
-        let lock_time = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
-        offset += 4;
+    pub fn build(self) -> BitcoinTransaction {
+        BitcoinTransaction::new(self.version, self.inputs, self.outputs, self.lock_time)
+    }
+}
 
-        Ok((Self::new(version, inputs, lock_time), offset))
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -265,7 +2745,7 @@ impl fmt::Display for BitcoinTransaction {
             writeln!(
                 f,
                 "    Previous Output TXID: {}",
-                hex::encode(input.previous_output.txid.0)
+                input.previous_output.txid.to_display_string()
             )?;
             writeln!(
                 f,
@@ -279,7 +2759,564 @@ impl fmt::Display for BitcoinTransaction {
                 hex::encode(&input.script_sig.bytes)
             )?;
             writeln!(f, "    Sequence: {}", input.sequence)?;
+            if let Some(stack) = self.witnesses.get(i).filter(|stack| !stack.is_empty()) {
+                writeln!(f, "    Witness ({} items):", stack.len())?;
+                for item in stack.iter() {
+                    writeln!(f, "      {}", hex::encode(item))?;
+                }
+            }
+        }
+        writeln!(f, "Outputs ({}):", self.outputs.len())?;
+        for (i, output) in self.outputs.iter().enumerate() {
+            writeln!(f, "  Output #{}:", i)?;
+            writeln!(f, "    Value: {}", output.value)?;
+            writeln!(
+                f,
+                "    ScriptPubKey ({} bytes): {}",
+                output.script_pubkey.len(),
+                hex::encode(&output.script_pubkey.bytes)
+            )?;
         }
         writeln!(f, "Lock Time: {}", self.lock_time)
     }
 }
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    pub fn serialized_len(&self) -> usize {
+        Self::SIZE
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (fields, _) = read_array::<{ Self::SIZE }>(bytes)?;
+        let (version, _) = read_array::<4>(&fields)?;
+        let (prev_blockhash, _) = read_array::<32>(&fields[4..])?;
+        let (merkle_root, _) = read_array::<32>(&fields[36..])?;
+        let header = BlockHeader {
+            version: i32::from_le_bytes(version),
+            prev_blockhash,
+            merkle_root,
+            time: read_u32_le(&fields[68..])?.0,
+            bits: read_u32_le(&fields[72..])?.0,
+            nonce: read_u32_le(&fields[76..])?.0,
+        };
+        Ok((header, Self::SIZE))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        Ok(BlockHeader {
+            version: i32::from_le_bytes(read_array_from(reader)?),
+            prev_blockhash: read_array_from(reader)?,
+            merkle_root: read_array_from(reader)?,
+            time: u32::from_le_bytes(read_array_from(reader)?),
+            bits: u32::from_le_bytes(read_array_from(reader)?),
+            nonce: u32::from_le_bytes(read_array_from(reader)?),
+        })
+    }
+
+    /// Double-SHA256 of the header in internal byte order; explorers show it
+    /// reversed, like a txid.
+    pub fn block_hash(&self) -> [u8; 32] {
+        sha256d(&self.to_bytes())
+    }
+}
+
+impl Encode for BlockHeader {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&self.prev_blockhash)?;
+        writer.write_all(&self.merkle_root)?;
+        writer.write_all(&self.time.to_le_bytes())?;
+        writer.write_all(&self.bits.to_le_bytes())?;
+        writer.write_all(&self.nonce.to_le_bytes())?;
+        Ok(Self::SIZE)
+    }
+}
+
+/// Decodes a block body: a CompactSize transaction count followed by that
+/// many transactions.
+pub fn decode_block_transactions(
+    bytes: &[u8],
+) -> Result<(Vec<BitcoinTransaction>, usize), BitcoinError> {
+    let mut txs = Vec::new();
+    let consumed = decode_vec_into(
+        bytes,
+        MIN_TRANSACTION_SIZE,
+        usize::MAX,
+        ("transaction count", "transaction"),
+        &mut txs,
+        |tx, _| BitcoinTransaction::from_bytes(tx),
+    )?;
+    Ok((txs, consumed))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (header, header_len) = BlockHeader::from_bytes(bytes)?;
+        let (transactions, body_len) = decode_block_transactions(&bytes[header_len..])?;
+        Ok((
+            Block {
+                header,
+                transactions,
+            },
+            header_len + body_len,
+        ))
+    }
+
+    /// Like `BitcoinTransaction::consensus_decode`, reads at most
+    /// `MAX_TRANSACTION_SIZE` bytes in total.
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        decode_with_budget(reader, |reader| {
+            let header = BlockHeader::consensus_decode(reader)?;
+            let count = CompactSize::consensus_decode(reader)?;
+            let count = stream_count(&count, MIN_TRANSACTION_SIZE, usize::MAX)?;
+            let mut transactions = Vec::new();
+            for _ in 0..count {
+                transactions.push(BitcoinTransaction::consensus_decode(reader)?);
+            }
+            Ok(Block {
+                header,
+                transactions,
+            })
+        })
+    }
+
+    /// Whether the header's merkle root matches the one computed from the
+    /// transactions' txids. Always false for a block without transactions.
+    pub fn verify_merkle_root(&self) -> bool {
+        let txids: Vec<Txid> = self
+            .transactions
+            .iter()
+            .map(BitcoinTransaction::txid)
+            .collect();
+        compute_merkle_root(&txids) == Some(self.header.merkle_root)
+    }
+}
+
+impl Encode for Block {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        let mut len = self.header.consensus_encode(writer)?;
+        len += CompactSize::new(self.transactions.len() as u64).consensus_encode(writer)?;
+        for tx in &self.transactions {
+            len += tx.consensus_encode(writer)?;
+        }
+        Ok(len)
+    }
+}
+
+/// Merkle root over txids in block order, in internal byte order to match
+/// `BlockHeader::merkle_root`. An odd level pairs its last hash with itself.
+pub fn compute_merkle_root(txids: &[Txid]) -> Option<[u8; 32]> {
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&pair[0]);
+                let mut concat = [0u8; 64];
+                concat[..32].copy_from_slice(&pair[0]);
+                concat[32..].copy_from_slice(right);
+                sha256d(&concat)
+            })
+            .collect();
+    }
+    level.first().copied()
+}
+
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// The hash type a signature commits to. Signing logic looks only at the low
+/// five bits and the ANYONECANPAY bit, but the full raw value is what gets
+/// hashed, so `NonStandard` keeps any value that is not one of the six named
+/// ones.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SighashType {
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+    /// A raw value such as 0x00, 0x04 or 0x41. Base types other than NONE
+    /// or SINGLE sign like ALL, as consensus code treats them.
+    NonStandard(u32),
+}
+
+impl SighashType {
+    /// Maps the six standard values to their variants and keeps anything
+    /// else as `NonStandard`, so `to_u32` always returns `n`.
+    pub fn from_u32(n: u32) -> SighashType {
+        match n {
+            SIGHASH_ALL => SighashType::All,
+            SIGHASH_NONE => SighashType::None,
+            SIGHASH_SINGLE => SighashType::Single,
+            0x81 => SighashType::AllPlusAnyoneCanPay,
+            0x82 => SighashType::NonePlusAnyoneCanPay,
+            0x83 => SighashType::SinglePlusAnyoneCanPay,
+            raw => SighashType::NonStandard(raw),
+        }
+    }
+
+    /// The raw value appended to the signature hash preimage.
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            SighashType::All => SIGHASH_ALL,
+            SighashType::None => SIGHASH_NONE,
+            SighashType::Single => SIGHASH_SINGLE,
+            SighashType::AllPlusAnyoneCanPay => SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+            SighashType::NonePlusAnyoneCanPay => SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+            SighashType::SinglePlusAnyoneCanPay => SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+            SighashType::NonStandard(raw) => *raw,
+        }
+    }
+}
+
+/// Pre-SegWit signature hash for `tx.inputs[input_index]`. `script_code` is
+/// the script being satisfied, normally the spent output's scriptPubKey; any
+/// OP_CODESEPARATOR handling is left to the caller.
+///
+/// SIGHASH_SINGLE without a matching output returns the 256-bit number one
+/// (`[1, 0, .., 0]` in internal byte order) instead of failing. This is
+/// intentional: Bitcoin Core has always signed that value here, so it is
+/// consensus behavior, and signatures over it are valid on chain.
+pub fn legacy_sighash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    script_code: &ScriptPubKey,
+    sighash_type: SighashType,
+) -> Result<[u8; 32], BitcoinError> {
+    if input_index >= tx.inputs.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let sighash_type = sighash_type.to_u32();
+    let base_type = sighash_type & 0x1F;
+    if base_type == SIGHASH_SINGLE && input_index >= tx.outputs.len() {
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        return Ok(one);
+    }
+
+    let mut inputs: Vec<TransactionInput> = tx
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let mut input = input.clone();
+            if i == input_index {
+                input.script_sig = ScriptSig::new(script_code.0.clone());
+            } else {
+                input.script_sig = ScriptSig::new(Script::new(Vec::new()));
+                if base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                    input.sequence = Sequence(0);
+                }
+            }
+            input
+        })
+        .collect();
+    if sighash_type & SIGHASH_ANYONECANPAY != 0 {
+        inputs.swap(0, input_index);
+        inputs.truncate(1);
+    }
+
+    let outputs = match base_type {
+        SIGHASH_NONE => Vec::new(),
+        SIGHASH_SINGLE => {
+            let mut outputs: Vec<TransactionOutput> = (0..input_index)
+                .map(|_| {
+                    TransactionOutput::new(u64::MAX, ScriptPubKey::new(Script::new(Vec::new())))
+                })
+                .collect();
+            outputs.push(tx.outputs[input_index].clone());
+            outputs
+        }
+        _ => tx.outputs.clone(),
+    };
+
+    let mut preimage = Vec::new();
+    BitcoinTransaction::new(tx.version, inputs, outputs, tx.lock_time)
+        .consensus_encode(&mut preimage)?;
+    preimage.extend_from_slice(&sighash_type.to_le_bytes());
+    Ok(sha256d(&preimage))
+}
+
+/// BIP 143 midstates shared by every input of a transaction, so signing many
+/// inputs hashes the prevouts, sequences and outputs only once.
+#[derive(Debug, Clone)]
+pub struct SegwitSighashCache<'a> {
+    tx: &'a BitcoinTransaction,
+    hash_prevouts: [u8; 32],
+    hash_sequence: [u8; 32],
+    hash_outputs: [u8; 32],
+}
+
+impl<'a> SegwitSighashCache<'a> {
+    pub fn new(tx: &'a BitcoinTransaction) -> Self {
+        Self {
+            tx,
+            hash_prevouts: sha256d(&tx.serialize_prevouts()),
+            hash_sequence: sha256d(&tx.serialize_sequences()),
+            hash_outputs: sha256d(&tx.serialize_outputs()),
+        }
+    }
+
+    /// `value` is the amount of the output being spent, in satoshis.
+    pub fn sighash(
+        &self,
+        input_index: usize,
+        script_code: &ScriptPubKey,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        let input = self
+            .tx
+            .inputs
+            .get(input_index)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let sighash_type = sighash_type.to_u32();
+        let base_type = sighash_type & 0x1F;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let zero = [0u8; 32];
+
+        let hash_prevouts = if anyone_can_pay {
+            zero
+        } else {
+            self.hash_prevouts
+        };
+        let hash_sequence =
+            if anyone_can_pay || base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                zero
+            } else {
+                self.hash_sequence
+            };
+        let hash_outputs = match (base_type, self.tx.outputs.get(input_index)) {
+            (SIGHASH_SINGLE, Some(output)) => sha256d(&output.to_bytes()),
+            (SIGHASH_SINGLE | SIGHASH_NONE, _) => zero,
+            _ => self.hash_outputs,
+        };
+
+        let mut preimage = Vec::new();
+        preimage.extend_from_slice(&self.tx.version.to_le_bytes());
+        preimage.extend_from_slice(&hash_prevouts);
+        preimage.extend_from_slice(&hash_sequence);
+        input.previous_output.consensus_encode(&mut preimage)?;
+        script_code.consensus_encode(&mut preimage)?;
+        preimage.extend_from_slice(&value.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.0.to_le_bytes());
+        preimage.extend_from_slice(&hash_outputs);
+        preimage.extend_from_slice(&self.tx.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+        Ok(sha256d(&preimage))
+    }
+}
+
+/// BIP 143 signature hash for a SegWit v0 input. Use `SegwitSighashCache`
+/// directly when signing several inputs of the same transaction.
+pub fn segwit_sighash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    script_code: &ScriptPubKey,
+    value: u64,
+    sighash_type: SighashType,
+) -> Result<[u8; 32], BitcoinError> {
+    SegwitSighashCache::new(tx).sighash(input_index, script_code, value, sighash_type)
+}
+
+/// A transaction paired with the scriptPubKey and value of the output each
+/// input spends, which is what a signer needs beyond the transaction itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignableTransaction {
+    tx: BitcoinTransaction,
+    prevouts: Vec<(ScriptPubKey, u64)>,
+}
+
+impl SignableTransaction {
+    /// Fails with `InvalidFormat` unless there is exactly one prevout per input.
+    pub fn new(
+        tx: BitcoinTransaction,
+        prevouts: Vec<(ScriptPubKey, u64)>,
+    ) -> Result<Self, BitcoinError> {
+        if prevouts.len() != tx.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Self { tx, prevouts })
+    }
+
+    pub fn tx(&self) -> &BitcoinTransaction {
+        &self.tx
+    }
+
+    pub fn input_prevout(&self, index: usize) -> Option<(&ScriptPubKey, u64)> {
+        self.prevouts
+            .get(index)
+            .map(|(script_pubkey, value)| (script_pubkey, *value))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+    Signet,
+}
+
+impl Network {
+    /// The message start bytes that prefix every P2P message. Signet uses the
+    /// default signet challenge.
+    pub fn magic(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xF9, 0xBE, 0xB4, 0xD9],
+            Network::Testnet => [0x0B, 0x11, 0x09, 0x07],
+            Network::Regtest => [0xFA, 0xBF, 0xB5, 0xDA],
+            Network::Signet => [0x0A, 0x03, 0xCF, 0x40],
+        }
+    }
+}
+
+pub fn strip_magic(bytes: &[u8], network: Network) -> Result<&[u8], BitcoinError> {
+    let (magic, rest) = bytes
+        .split_first_chunk::<4>()
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    if *magic != network.magic() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(rest)
+}
+
+/// Block hash in internal byte order, like `Txid`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct BlockHash(pub [u8; 32]);
+
+impl BlockHash {
+    pub fn to_display_string(&self) -> String {
+        let mut bytes = self.0;
+        bytes.reverse();
+        hex::encode(bytes)
+    }
+}
+
+impl From<[u8; 32]> for BlockHash {
+    fn from(bytes: [u8; 32]) -> Self {
+        BlockHash(bytes)
+    }
+}
+
+pub const MSG_TX: u32 = 1;
+pub const MSG_BLOCK: u32 = 2;
+pub const MSG_WITNESS_FLAG: u32 = 1 << 30;
+
+/// One entry of an `inv` or `getdata` message: a 4-byte little-endian type
+/// code followed by the 32-byte hash.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Inventory {
+    Tx(Txid),
+    Block(BlockHash),
+    WitnessTx(Txid),
+    WitnessBlock(BlockHash),
+}
+
+impl Inventory {
+    pub const SIZE: usize = 36;
+
+    pub fn type_code(&self) -> u32 {
+        match self {
+            Inventory::Tx(_) => MSG_TX,
+            Inventory::Block(_) => MSG_BLOCK,
+            Inventory::WitnessTx(_) => MSG_TX | MSG_WITNESS_FLAG,
+            Inventory::WitnessBlock(_) => MSG_BLOCK | MSG_WITNESS_FLAG,
+        }
+    }
+
+    /// Fails with `InvalidFormat` for type codes other than the four above.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (fields, _) = read_array::<{ Self::SIZE }>(bytes)?;
+        let (type_code, _) = read_u32_le(&fields)?;
+        let (hash, _) = read_array::<32>(&fields[4..])?;
+        let inventory = match type_code {
+            MSG_TX => Inventory::Tx(Txid(hash)),
+            MSG_BLOCK => Inventory::Block(BlockHash(hash)),
+            code if code == MSG_TX | MSG_WITNESS_FLAG => Inventory::WitnessTx(Txid(hash)),
+            code if code == MSG_BLOCK | MSG_WITNESS_FLAG => {
+                Inventory::WitnessBlock(BlockHash(hash))
+            }
+            _ => return Err(BitcoinError::InvalidFormat),
+        };
+        Ok((inventory, Self::SIZE))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, BitcoinError> {
+        let bytes: [u8; Self::SIZE] = read_array_from(reader)?;
+        Ok(Self::from_bytes(&bytes)?.0)
+    }
+
+    fn hash(&self) -> &[u8; 32] {
+        match self {
+            Inventory::Tx(txid) | Inventory::WitnessTx(txid) => &txid.0,
+            Inventory::Block(hash) | Inventory::WitnessBlock(hash) => &hash.0,
+        }
+    }
+}
+
+impl Encode for Inventory {
+    fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, BitcoinError> {
+        writer.write_all(&self.type_code().to_le_bytes())?;
+        writer.write_all(self.hash())?;
+        Ok(Self::SIZE)
+    }
+}
+
+/// Base58Check: base58 over the payload followed by the first four bytes of
+/// its double SHA-256.
+pub mod base58 {
+    use super::{BitcoinError, sha256d};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    /// `payload` includes the version byte, if any.
+    pub fn encode_check(payload: &[u8]) -> String {
+        let mut bytes = Vec::with_capacity(payload.len() + 4);
+        bytes.extend_from_slice(payload);
+        bytes.extend_from_slice(&sha256d(payload)[..4]);
+        bs58::encode(bytes).into_string()
+    }
+
+    /// Returns the payload, version byte included. Bad characters, input
+    /// shorter than the checksum and checksum mismatches are `InvalidFormat`.
+    pub fn decode_check(s: &str) -> Result<Vec<u8>, BitcoinError> {
+        let mut bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        let split = bytes
+            .len()
+            .checked_sub(4)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        if sha256d(&bytes[..split])[..4] != bytes[split..] {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        bytes.truncate(split);
+        Ok(bytes)
+    }
+}
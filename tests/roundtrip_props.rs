@@ -0,0 +1,34 @@
+//! Property-based round trips over `Arbitrary` values.
+//! Run with `cargo test --features arbitrary`.
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use proptest::prelude::*;
+use rust_week_3_exercises::*;
+
+macro_rules! roundtrip_test {
+    ($name:ident, $ty:ty) => {
+        proptest! {
+            #[test]
+            fn $name(seed in proptest::collection::vec(any::<u8>(), 0..1024)) {
+                let value = <$ty>::arbitrary(&mut Unstructured::new(&seed)).unwrap();
+                let bytes = value.to_bytes();
+                prop_assert_eq!(<$ty>::from_bytes(&bytes), Ok((value, bytes.len())));
+            }
+        }
+    };
+}
+
+roundtrip_test!(compact_size_roundtrip, CompactSize);
+roundtrip_test!(outpoint_roundtrip, OutPoint);
+roundtrip_test!(script_roundtrip, Script);
+roundtrip_test!(transaction_input_roundtrip, TransactionInput);
+roundtrip_test!(transaction_roundtrip, BitcoinTransaction);
+
+proptest! {
+    #[test]
+    fn txid_display_roundtrip(seed in proptest::collection::vec(any::<u8>(), 32)) {
+        let txid = Txid::arbitrary(&mut Unstructured::new(&seed)).unwrap();
+        prop_assert_eq!(Txid::from_display_string(&txid.to_display_string()), Ok(txid));
+    }
+}
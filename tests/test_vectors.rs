@@ -0,0 +1,146 @@
+use rust_week_3_exercises::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Vector {
+        hex: &'static str,
+        txid: &'static str,
+        version: u32,
+        inputs: usize,
+        outputs: usize,
+        lock_time: u32,
+        total_output_value: u64,
+        first_prevout: &'static str,
+        coinbase: bool,
+        weight: usize,
+    }
+
+    // Mainnet transactions; expected fields were checked against rust-bitcoin.
+    const VECTORS: &[Vector] = &[
+        // Genesis block coinbase.
+        Vector {
+            hex: "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000",
+            txid: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            version: 1,
+            inputs: 1,
+            outputs: 1,
+            lock_time: 0,
+            total_output_value: 5_000_000_000,
+            first_prevout: "0000000000000000000000000000000000000000000000000000000000000000:4294967295",
+            coinbase: true,
+            weight: 816,
+        },
+        // Block 170: the first bitcoin transfer, Satoshi to Hal Finney.
+        Vector {
+            hex: "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000",
+            txid: "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            version: 1,
+            inputs: 1,
+            outputs: 2,
+            lock_time: 0,
+            total_output_value: 5_000_000_000,
+            first_prevout: "0437cd7f8525ceed2324359c2d0ba26006d92d856a9c20fa0241106ee5a597c9:0",
+            coinbase: false,
+            weight: 1100,
+        },
+        // Three P2PK inputs consolidated into one output, block 00000000b0c5a240....
+        Vector {
+            hex: "010000000321f75f3139a013f50f315b23b0c9a2b6eac31e2bec98e5891c924664889942260000000049483045022100cb2c6b346a978ab8c61b18b5e9397755cbd17d6eb2fe0083ef32e067fa6c785a02206ce44e613f31d9a6b0517e46f3db1576e9812cc98d159bfdaf759a5014081b5c01ffffffff79cda0945903627c3da1f85fc95d0b8ee3e76ae0cfdc9a65d09744b1f8fc85430000000049483045022047957cdd957cfd0becd642f6b84d82f49b6cb4c51a91f49246908af7c3cfdf4a022100e96b46621f1bffcf5ea5982f88cef651e9354f5791602369bf5a82a6cd61a62501fffffffffe09f5fe3ffbf5ee97a54eb5e5069e9da6b4856ee86fc52938c2f979b0f38e82000000004847304402204165be9a4cbab8049e1af9723b96199bfd3e85f44c6b4c0177e3962686b26073022028f638da23fc003760861ad481ead4099312c60030d4cb57820ce4d33812a5ce01ffffffff01009d966b01000000434104ea1feff861b51fe3f5f8a3b12d0f4712db80e919548a80839fc47c6a21e66d957e9c5d8cd108c7a2d2324bad71f9904ac0ae7336507d785b17a2c115e427a32fac00000000",
+            txid: "a3b0e9e7cddbbe78270fa4182a7675ff00b92872d8df7d14265a2b1e379a9d33",
+            version: 1,
+            inputs: 3,
+            outputs: 1,
+            lock_time: 0,
+            total_output_value: 6_100_000_000,
+            first_prevout: "264299886446921c89e598ec2b1ec3eab6a2c9b0235b310ff513a039315ff721:0",
+            coinbase: false,
+            weight: 1708,
+        },
+        // Single P2WPKH input paying to P2SH.
+        Vector {
+            hex: "02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c0100000000ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd2710e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc00000000",
+            txid: "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206",
+            version: 2,
+            inputs: 1,
+            outputs: 1,
+            lock_time: 0,
+            total_output_value: 506_078,
+            first_prevout: "7cac3cf9a112cf04901a51d605058615d56ffe6d04b45270e89d1720ea955859:1",
+            coinbase: false,
+            weight: 442,
+        },
+        // Four P2SH-P2WPKH inputs, three outputs.
+        Vector {
+            hex: "020000000001040aacd2c49f5f3c0968cfa8caf9d5761436d95385252e3abb4de8f5dcf8a582f20000000017160014bcadb2baea98af0d9a902e53a7e9adff43b191e9feffffff96cd3c93cac3db114aafe753122bd7d1afa5aa4155ae04b3256344ecca69d72001000000171600141d9984579ceb5c67ebfbfb47124f056662fe7adbfeffffffc878dd74d3a44072eae6178bb94b9253177db1a5aaa6d068eb0e4db7631762e20000000017160014df2a48cdc53dae1aba7aa71cb1f9de089d75aac3feffffffe49f99275bc8363f5f593f4eec371c51f62c34ff11cc6d8d778787d340d6896c0100000017160014229b3b297a0587e03375ab4174ef56eeb0968735feffffff03360d0f00000000001976a9149f44b06f6ee92ddbc4686f71afe528c09727a5c788ac24281b00000000001976a9140277b4f68ff20307a2a9f9b4487a38b501eb955888ac227c0000000000001976a9148020cd422f55eef8747a9d418f5441030f7c9c7788ac0247304402204aa3bd9682f9a8e101505f6358aacd1749ecf53a62b8370b97d59243b3d6984f02200384ad449870b0e6e89c92505880411285ecd41cf11e7439b973f13bad97e53901210205b392ffcb83124b1c7ce6dd594688198ef600d34500a7f3552d67947bbe392802473044022033dfd8d190a4ae36b9f60999b217c775b96eb10dee3a1ff50fb6a75325719106022005872e4e36d194e49ced2ebcf8bb9d843d842e7b7e0eb042f4028396088d292f012103c9d7cbf369410b090480de2aa15c6c73d91b9ffa7d88b90724614b70be41e98e0247304402207d952de9e59e4684efed069797e3e2d993e9f98ec8a9ccd599de43005fe3f713022076d190cc93d9513fc061b1ba565afac574e02027c9efbfa1d7b71ab8dbb21e0501210313ad44bc030cc6cb111798c2bf3d2139418d751c1e79ec4e837ce360cc03b97a024730440220029e75edb5e9413eb98d684d62a077b17fa5b7cc19349c1e8cc6c4733b7b7452022048d4b9cae594f03741029ff841e35996ef233701c1ea9aa55c301362ea2e2f68012103590657108a72feb8dc1dec022cf6a230bb23dc7aaa52f4032384853b9f8388baf9d20700",
+            txid: "915c4437f4288418132224116216fedb801f37418806303aa3a4bf4ff5c8fb1c",
+            version: 2,
+            inputs: 4,
+            outputs: 3,
+            lock_time: 512_761,
+            total_output_value: 2_797_948,
+            first_prevout: "f282a5f8dcf5e84dbb3a2e258553d9361476d5f9caa8cf68093c5f9fc4d2ac0a:0",
+            coinbase: false,
+            weight: 1902,
+        },
+        // Spends the transaction above. Mixes a legacy P2PKH input (empty
+        // witness) with two P2SH-P2WPKH inputs and has six outputs.
+        Vector {
+            hex: "020000000001031cfbc8f54fbfa4a33a30068841371f80dbfe166211242213188428f437445c91000000006a47304402206fbcec8d2d2e740d824d3d36cc345b37d9f65d665a99f5bd5c9e8d42270a03a8022013959632492332200c2908459547bf8dbf97c65ab1a28dec377d6f1d41d3d63e012103d7279dfb90ce17fe139ba60a7c41ddf605b25e1c07a4ddcb9dfef4e7d6710f48feffffff476222484f5e35b3f0e43f65fc76e21d8be7818dd6a989c160b1e5039b7835fc00000000171600140914414d3c94af70ac7e25407b0689e0baa10c77feffffffa83d954a62568bbc99cc644c62eb7383d7c2a2563041a0aeb891a6a4055895570000000017160014795d04cc2d4f31480d9a3710993fbd80d04301dffeffffff06fef72f000000000017a91476fd7035cd26f1a32a5ab979e056713aac25796887a5000f00000000001976a914b8332d502a529571c6af4be66399cd33379071c588ac3fda0500000000001976a914fc1d692f8de10ae33295f090bea5fe49527d975c88ac522e1b00000000001976a914808406b54d1044c429ac54c0e189b0d8061667e088ac6eb68501000000001976a914dfab6085f3a8fb3e6710206a5a959313c5618f4d88acbba20000000000001976a914eb3026552d7e3f3073457d0bee5d4757de48160d88ac0002483045022100bee24b63212939d33d513e767bc79300051f7a0d433c3fcf1e0e3bf03b9eb1d70220588dc45a9ce3a939103b4459ce47500b64e23ab118dfc03c9caa7d6bfc32b9c601210354fd80328da0f9ae6eef2b3a81f74f9a6f66761fadf96f1d1d22b1fd6845876402483045022100e29c7e3a5efc10da6269e5fc20b6a1cb8beb92130cc52c67e46ef40aaa5cac5f0220644dd1b049727d991aece98a105563416e10a5ac4221abac7d16931842d5c322012103960b87412d6e169f30e12106bdf70122aabb9eb61f455518322a18b920a4dfa887d30700",
+            txid: "0543411b2e7fbd8af4c46c593049dbac2d4007c0f5f12e4e5c71a07566e787c4",
+            version: 2,
+            inputs: 3,
+            outputs: 6,
+            lock_time: 512_903,
+            total_output_value: 31_873_629,
+            first_prevout: "915c4437f4288418132224116216fedb801f37418806303aa3a4bf4ff5c8fb1c:0",
+            coinbase: false,
+            weight: 2167,
+        },
+    ];
+
+    #[test]
+    fn test_mainnet_vectors_decode() {
+        for v in VECTORS {
+            let bytes = hex::decode(v.hex).unwrap();
+            let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(consumed, bytes.len(), "{}", v.txid);
+            assert_eq!(tx.txid().to_display_string(), v.txid);
+            assert_eq!(tx.version, v.version, "{}", v.txid);
+            assert_eq!(tx.inputs.len(), v.inputs, "{}", v.txid);
+            assert_eq!(tx.outputs.len(), v.outputs, "{}", v.txid);
+            assert_eq!(tx.lock_time, v.lock_time, "{}", v.txid);
+            assert_eq!(
+                tx.total_output_value().unwrap(),
+                v.total_output_value,
+                "{}",
+                v.txid
+            );
+            assert_eq!(
+                tx.inputs[0].previous_output.to_string(),
+                v.first_prevout,
+                "{}",
+                v.txid
+            );
+            assert_eq!(tx.is_coinbase(), v.coinbase, "{}", v.txid);
+            assert_eq!(tx.weight(), v.weight, "{}", v.txid);
+        }
+    }
+
+    #[test]
+    fn test_mainnet_vectors_roundtrip() {
+        for v in VECTORS {
+            let bytes = hex::decode(v.hex).unwrap();
+            let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(tx.to_bytes(), bytes, "{}", v.txid);
+
+            let mut reader = bytes.as_slice();
+            assert_eq!(
+                BitcoinTransaction::consensus_decode(&mut reader).unwrap(),
+                tx
+            );
+            assert!(reader.is_empty());
+        }
+    }
+}
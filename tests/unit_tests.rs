@@ -4,6 +4,13 @@ use rust_week_3_exercises::*;
 mod tests {
     use super::*;
 
+    const BLOCK_170_TX_HEX: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+    const BLOCK_170_TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    const SEGWIT_TX_HEX: &str = "02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c0100000000ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd2710e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc00000000";
+    const SEGWIT_TXID: &str = "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206";
+    const SEGWIT_WTXID: &str = "80b7d8a82d5d5bf92905b06f2014dd699e03837ca172e3a59d51426ebbe3e7f5";
+
     fn dummy_txid(val: u8) -> [u8; 32] {
         let mut txid = [0u8; 32];
         txid[31] = val;
@@ -34,6 +41,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compact_size_encoded_len() {
+        let cases = [
+            (0u64, 1),
+            (0xFC, 1),
+            (0xFD, 3),
+            (0xFFFF, 3),
+            (0x10000, 5),
+            (0xFFFFFFFF, 5),
+            (0x100000000, 9),
+            (u64::MAX, 9),
+        ];
+        for (value, len) in cases {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.encoded_len(), len, "{}", value);
+            assert_eq!(cs.to_bytes().len(), len);
+        }
+    }
+
+    #[test]
+    fn test_compact_size_strict_decoding() {
+        let overlong = vec![
+            vec![0xFD, 0xFC, 0x00],
+            vec![0xFE, 0xFF, 0xFF, 0x00, 0x00],
+            vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00],
+        ];
+        for bytes in overlong {
+            assert!(CompactSize::from_bytes(&bytes).is_ok());
+            assert_eq!(
+                CompactSize::from_bytes_strict(&bytes),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+
+        let minimal = vec![
+            (0xFCu64, vec![0xFC]),
+            (0xFFFF, vec![0xFD, 0xFF, 0xFF]),
+            (0xFFFFFFFF, vec![0xFE, 0xFF, 0xFF, 0xFF, 0xFF]),
+        ];
+        for (value, bytes) in minimal {
+            let (decoded, consumed) = CompactSize::from_bytes_strict(&bytes).unwrap();
+            assert_eq!(decoded.value, value);
+            assert_eq!(consumed, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_compact_size_to_usize() {
+        assert_eq!(CompactSize::new(0xFFFFFFFF).to_usize(), Ok(0xFFFFFFFF));
+        #[cfg(target_pointer_width = "32")]
+        {
+            assert_eq!(
+                CompactSize::new(0x100000000).to_usize(),
+                Err(BitcoinError::InvalidFormat)
+            );
+            let mut bytes = vec![0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+            bytes.extend_from_slice(&[0u8; 16]);
+            assert_eq!(Script::from_bytes(&bytes), Err(BitcoinError::InvalidFormat));
+        }
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(CompactSize::new(u64::MAX).to_usize(), Ok(usize::MAX));
+    }
+
+    #[test]
+    fn test_bitcoin_error_display() {
+        assert_eq!(
+            BitcoinError::InsufficientBytes.to_string(),
+            "insufficient bytes to decode"
+        );
+        assert_eq!(BitcoinError::InvalidFormat.to_string(), "invalid format");
+
+        let boxed: Box<dyn std::error::Error> = Box::new(BitcoinError::InvalidFormat);
+        assert_eq!(boxed.to_string(), "invalid format");
+    }
+
     #[test]
     fn test_outpoint_roundtrip() {
         let txid = dummy_txid(0xCC);
@@ -45,6 +127,67 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_outpoint_null() {
+        let null = OutPoint::null();
+        assert!(null.is_null());
+        assert_eq!(null.to_bytes(), [[0u8; 32].as_slice(), &[0xFF; 4]].concat());
+        assert!(!OutPoint::new([0u8; 32], 0).is_null());
+        assert!(!OutPoint::new(dummy_txid(1), 0xFFFFFFFF).is_null());
+    }
+
+    #[test]
+    fn test_outpoint_display_and_from_str() {
+        let s = format!("{}:1", BLOCK_170_TXID);
+        let outpoint: OutPoint = s.parse().unwrap();
+        assert_eq!(outpoint.vout, 1);
+        assert_eq!(outpoint.txid.0[0], 0x16);
+        assert_eq!(outpoint.to_string(), s);
+
+        for bad in [
+            BLOCK_170_TXID.to_string(),
+            format!("{}:", BLOCK_170_TXID),
+            format!("{}:-1", BLOCK_170_TXID),
+            format!("{}:4294967296", BLOCK_170_TXID),
+            "abcd:0".to_string(),
+        ] {
+            assert_eq!(bad.parse::<OutPoint>(), Err(BitcoinError::InvalidFormat));
+        }
+    }
+
+    #[test]
+    fn test_outpoint_in_collections() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let outpoints = [
+            OutPoint::new(dummy_txid(2), 0),
+            OutPoint::new(dummy_txid(1), 5),
+            OutPoint::new(dummy_txid(1), 0),
+            OutPoint::new(dummy_txid(2), 0),
+        ];
+
+        let set: HashSet<OutPoint> = outpoints.iter().cloned().collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&OutPoint::new(dummy_txid(1), 5)));
+        assert!(!set.contains(&OutPoint::new(dummy_txid(3), 0)));
+
+        let ordered: Vec<OutPoint> = outpoints
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            ordered,
+            vec![
+                OutPoint::new(dummy_txid(1), 0),
+                OutPoint::new(dummy_txid(1), 5),
+                OutPoint::new(dummy_txid(2), 0),
+            ]
+        );
+        assert!(Txid([0x01; 32]) < Txid([0x02; 32]));
+    }
+
     #[test]
     fn test_script_roundtrip() {
         let script_data = vec![0x76, 0xA9, 0x14, 0x88, 0xAC];
@@ -55,39 +198,676 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_script_ref_borrows_from_buffer() {
+        let bytes = [0x03, 0x51, 0x52, 0x53, 0xFF];
+        let (script_ref, consumed) = ScriptRef::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, 4);
+        assert_eq!(script_ref.0, &bytes[1..4]);
+        assert!(std::ptr::eq(script_ref.0.as_ptr(), bytes[1..].as_ptr()));
+        assert_eq!(script_ref.to_owned(), Script::new(vec![0x51, 0x52, 0x53]));
+
+        assert_eq!(
+            ScriptRef::from_bytes(&bytes[..3]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_script_instructions_p2pkh() {
+        let script_pubkey =
+            Script::new(hex::decode("76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac").unwrap());
+        let instructions = script_pubkey.instructions().unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::Op(OP_DUP),
+                Instruction::Op(OP_HASH160),
+                Instruction::PushBytes(
+                    hex::decode("89abcdefabbaabbaabbaabbaabbaabbaabbaabba").unwrap()
+                ),
+                Instruction::Op(OP_EQUALVERIFY),
+                Instruction::Op(OP_CHECKSIG),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_instructions_pushdata() {
+        let mut bytes = vec![OP_PUSHDATA1, 0x02, 0xAA, 0xBB];
+        bytes.extend_from_slice(&[OP_PUSHDATA2, 0x01, 0x00, 0xCC]);
+        bytes.extend_from_slice(&[OP_PUSHDATA4, 0x01, 0x00, 0x00, 0x00, 0xDD]);
+        assert_eq!(
+            Script::new(bytes).instructions().unwrap(),
+            vec![
+                Instruction::PushBytes(vec![0xAA, 0xBB]),
+                Instruction::PushBytes(vec![0xCC]),
+                Instruction::PushBytes(vec![0xDD]),
+            ]
+        );
+
+        for truncated in [vec![0x03, 0x01, 0x02], vec![OP_PUSHDATA2, 0x01]] {
+            assert_eq!(
+                Script::new(truncated).instructions(),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_hex_and_asm() {
+        let p2pkh_hex = "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac";
+        let script = Script::from_hex(p2pkh_hex).unwrap();
+        assert_eq!(script.to_hex(), p2pkh_hex);
+        assert_eq!(
+            script.to_asm(),
+            "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG"
+        );
+
+        let multisig = Script::new(vec![0x52, 0x01, 0xAA, OP_PUSHDATA1, 0x01, 0xBB, 0x52, 0xAE]);
+        // Short pushes are script numbers: 0xaa and 0xbb have the sign bit set.
+        assert_eq!(multisig.to_asm(), "2 -42 -59 2 OP_CHECKMULTISIG");
+        assert_eq!(
+            Script::new(vec![OP_0, 0x4F, 0x60, 0xB1, 0xBA, 0xBB, 0xFF]).to_asm(),
+            "0 -1 16 OP_CHECKLOCKTIMEVERIFY OP_CHECKSIGADD OP_UNKNOWN OP_INVALIDOPCODE"
+        );
+        assert_eq!(
+            Script::from_hex("0302a1070480000000040100008000")
+                .unwrap()
+                .to_asm(),
+            "499970 128 -1 0"
+        );
+        assert_eq!(
+            Script::new(vec![OP_RETURN, 0x05, 0x01]).to_asm(),
+            "OP_RETURN [error]"
+        );
+        assert_eq!(Script::new(vec![]).to_asm(), "");
+
+        assert_eq!(Script::from_hex("abc"), Err(BitcoinError::InvalidFormat));
+        assert_eq!(Script::from_hex("zz"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_script_type_classification() {
+        let cases = [
+            (
+                "76a914162c5ea71c0b23f5b9022ef047c4a86470a5b07088ac",
+                ScriptType::P2PKH,
+            ),
+            (
+                "a914162c5ea71c0b23f5b9022ef047c4a86470a5b07087",
+                ScriptType::P2SH,
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                ScriptType::P2WPKH,
+            ),
+            (
+                "0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d",
+                ScriptType::P2WSH,
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                ScriptType::P2TR,
+            ),
+            (
+                "6a24aa21a9edf91c46b49eb8a29089980f02ee6b57e7d63d33b18b4fddac2bcd7db2a3983704",
+                ScriptType::OpReturn,
+            ),
+            (
+                "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac",
+                ScriptType::NonStandard,
+            ),
+            // P2WPKH prefix with a 19-byte program is not a valid witness program.
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433b",
+                ScriptType::NonStandard,
+            ),
+        ];
+        for (script_hex, expected) in cases {
+            let script = Script::new(hex::decode(script_hex).unwrap());
+            assert_eq!(script.script_type(), expected, "{}", script_hex);
+        }
+    }
+
+    #[test]
+    fn test_witness_version_and_program() {
+        let cases = [
+            ("0014751e76e8199196d454941c45d1b3a323f1433bd6", 0, 20),
+            (
+                "0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d",
+                0,
+                32,
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                1,
+                32,
+            ),
+            ("6002aabb", 16, 2),
+        ];
+        for (script_hex, version, program_len) in cases {
+            let script = Script::from_hex(script_hex).unwrap();
+            assert_eq!(script.witness_version(), Some(version), "{}", script_hex);
+            assert_eq!(script.witness_program(), Some(&script[2..]));
+            assert_eq!(script.witness_program().unwrap().len(), program_len);
+        }
+
+        for not_program in [
+            "76a914162c5ea71c0b23f5b9022ef047c4a86470a5b07088ac",
+            "0001aa",
+            "0014751e76e8199196d454941c45d1b3a323f1433b",
+            "4f02aabb",
+            "",
+        ] {
+            let script = Script::from_hex(not_program).unwrap();
+            assert_eq!(script.witness_version(), None, "{}", not_program);
+            assert_eq!(script.witness_program(), None);
+        }
+    }
+
     #[test]
     fn test_tx_input_roundtrip() {
         let outpoint = OutPoint::new(dummy_txid(1), 0);
         let script = Script::new(vec![0x01, 0x02]);
-        let input = TransactionInput::new(outpoint.clone(), script.clone(), 0xFFFFFFFF);
+        let input =
+            TransactionInput::new(outpoint.clone(), ScriptSig::new(script.clone()), 0xFFFFFFFF);
         let bytes = input.to_bytes();
         let (parsed, consumed) = TransactionInput::from_bytes(&bytes).unwrap();
         assert_eq!(parsed, input);
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_tx_output_roundtrip() {
+        let script = Script::new(vec![0x76, 0xA9, 0x14, 0x88, 0xAC]);
+        let output = TransactionOutput::new(50_000, ScriptPubKey::new(script));
+        let bytes = output.to_bytes();
+        let (parsed, consumed) = TransactionOutput::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, output);
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(
+            TransactionOutput::from_bytes(&bytes[..7]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_amount_conversions() {
+        let amount = Amount::from_btc(0.0005).unwrap();
+        assert_eq!(amount.to_sat(), 50_000);
+        assert_eq!(amount.to_btc(), 0.0005);
+        assert_eq!(amount.to_string(), "0.00050000 BTC");
+        assert_eq!(
+            Amount::from_sat(2_100_000_000_000_000).to_string(),
+            "21000000.00000000 BTC"
+        );
+
+        assert_eq!(Amount::from_btc(-1.0), Err(BitcoinError::InvalidFormat));
+        assert_eq!(Amount::from_btc(f64::NAN), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            Amount::from_btc(21_000_001.0),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let output = TransactionOutput::new(1234, ScriptPubKey::new(Script::new(vec![])));
+        assert_eq!(output.amount(), Amount::from_sat(1234));
+    }
+
+    #[test]
+    fn test_amount_checked_arithmetic() {
+        let a = Amount::from_sat(MAX_MONEY - 1);
+        let b = Amount::from_sat(1);
+        assert_eq!(a.checked_add(b), Some(Amount::from_sat(MAX_MONEY)));
+        assert_eq!(a.checked_add(Amount::from_sat(2)), None);
+        assert_eq!(Amount::from_sat(u64::MAX).checked_add(b), None);
+
+        assert_eq!(b.checked_sub(b), Some(Amount::from_sat(0)));
+        assert_eq!(b.checked_sub(Amount::from_sat(2)), None);
+    }
+
     #[test]
     fn test_bitcoin_tx_roundtrip() {
         let inputs = vec![TransactionInput::new(
             OutPoint::new(dummy_txid(1), 0),
-            Script::new(vec![0x01, 0x02]),
+            ScriptSig::new(Script::new(vec![0x01, 0x02])),
             0xFFFFFFFF,
         )];
-        let tx = BitcoinTransaction::new(2, inputs.clone(), 1000);
+        let outputs = vec![
+            TransactionOutput::new(10_000, ScriptPubKey::new(Script::new(vec![0x51]))),
+            TransactionOutput::new(20_000, ScriptPubKey::new(Script::new(vec![0x52, 0x53]))),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), outputs, 1000);
+        let bytes = tx.to_bytes();
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_transaction_builder() {
+        let default_tx = TransactionBuilder::new().build();
+        assert_eq!(default_tx, BitcoinTransaction::new(2, vec![], vec![], 0));
+
+        let outpoint = OutPoint::new(dummy_txid(1), 0);
+        let tx = TransactionBuilder::new()
+            .version(1)
+            .add_input(
+                outpoint.clone(),
+                ScriptSig::new(Script::new(vec![0x01])),
+                0xFFFFFFFD,
+            )
+            .add_output(1000, ScriptPubKey::new(Script::new(vec![0x51])))
+            .add_output(2000, ScriptPubKey::new(Script::new(vec![0x52])))
+            .lock_time(800_000)
+            .build();
+        let expected = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                outpoint,
+                ScriptSig::new(Script::new(vec![0x01])),
+                0xFFFFFFFD,
+            )],
+            vec![
+                TransactionOutput::new(1000, ScriptPubKey::new(Script::new(vec![0x51]))),
+                TransactionOutput::new(2000, ScriptPubKey::new(Script::new(vec![0x52]))),
+            ],
+            800_000,
+        );
+        assert_eq!(tx, expected);
+    }
+
+    #[test]
+    fn test_bitcoin_tx_fee() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 0),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            ),
+        ];
+        let outputs = vec![
+            TransactionOutput::new(30_000, ScriptPubKey::new(Script::new(vec![]))),
+            TransactionOutput::new(15_000, ScriptPubKey::new(Script::new(vec![]))),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, outputs, 0);
+
+        assert_eq!(tx.total_output_value(), Ok(45_000));
+        assert_eq!(tx.fee(&[25_000, 21_000]), Ok(1_000));
+        assert_eq!(tx.fee(&[25_000]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.fee(&[20_000, 20_000]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.fee(&[u64::MAX, 1]), Err(BitcoinError::InvalidFormat));
+
+        let overflowing = BitcoinTransaction::new(
+            2,
+            vec![],
+            vec![
+                TransactionOutput::new(u64::MAX, ScriptPubKey::new(Script::new(vec![]))),
+                TransactionOutput::new(1, ScriptPubKey::new(Script::new(vec![]))),
+            ],
+            0,
+        );
+        assert_eq!(
+            overflowing.total_output_value(),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_bitcoin_tx_txid() {
+        // First bitcoin transfer between two people, mined in block 170.
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+
+        let mut expected = hex::decode(BLOCK_170_TXID).unwrap();
+        expected.reverse();
+        assert_eq!(tx.txid().0.to_vec(), expected);
+    }
+
+    #[test]
+    fn test_segwit_tx_roundtrip() {
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.inputs.len(), 1);
+        assert_eq!(tx.outputs.len(), 1);
+        assert_eq!(tx.witnesses.len(), 1);
+        assert_eq!(tx.witnesses[0].len(), 2);
+        assert_eq!(tx.witnesses[0].get(1).unwrap().len(), 33);
+        assert_eq!(tx.to_bytes(), bytes);
+        assert_eq!(tx.txid().to_display_string(), SEGWIT_TXID);
+    }
+
+    #[test]
+    fn test_segwit_tx_built_roundtrip() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 1),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            ),
+        ];
+        let outputs = vec![TransactionOutput::new(
+            1000,
+            ScriptPubKey::new(Script::new(vec![0x00, 0x14])),
+        )];
+        let mut tx = BitcoinTransaction::new(2, inputs, outputs, 0);
+        tx.witnesses = vec![
+            Witness::new(),
+            Witness::from(vec![vec![0x30; 71], vec![0x02; 33]]),
+        ];
+
         let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
         let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
         assert_eq!(parsed, tx);
         assert_eq!(consumed, bytes.len());
+
+        tx.witnesses.clear();
+        assert_ne!(tx.to_bytes()[4], 0x00);
+        assert_eq!(parsed.txid(), tx.txid());
+    }
+
+    #[test]
+    fn test_is_coinbase() {
+        let coinbase_input = TransactionInput::new(
+            OutPoint::new([0u8; 32], 0xFFFFFFFF),
+            ScriptSig::new(Script::new(vec![0x03, 0xda, 0x1b, 0x0e])),
+            0xFFFFFFFF,
+        );
+        let output =
+            TransactionOutput::new(5_000_000_000, ScriptPubKey::new(Script::new(vec![0x51])));
+        let coinbase = BitcoinTransaction::new(1, vec![coinbase_input.clone()], vec![output], 0);
+        assert!(coinbase.is_coinbase());
+
+        let spend = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            ScriptSig::new(Script::new(vec![])),
+            0,
+        );
+        let two_inputs = BitcoinTransaction::new(1, vec![coinbase_input, spend.clone()], vec![], 0);
+        assert!(!two_inputs.is_coinbase());
+
+        let null_txid_wrong_vout = TransactionInput::new(
+            OutPoint::new([0u8; 32], 0),
+            ScriptSig::new(Script::new(vec![])),
+            0,
+        );
+        assert!(!BitcoinTransaction::new(1, vec![null_txid_wrong_vout], vec![], 0).is_coinbase());
+        assert!(!BitcoinTransaction::new(1, vec![spend], vec![], 0).is_coinbase());
+    }
+
+    #[test]
+    fn test_locktime_kind() {
+        let mut tx = BitcoinTransaction::new(2, vec![], vec![], 0);
+        assert_eq!(tx.locktime_kind(), LockTimeKind::Disabled);
+
+        tx.lock_time = 499_999_999;
+        assert_eq!(tx.locktime_kind(), LockTimeKind::BlockHeight(499_999_999));
+
+        tx.lock_time = 500_000_000;
+        assert_eq!(tx.locktime_kind(), LockTimeKind::Timestamp(500_000_000));
+    }
+
+    #[test]
+    fn test_is_final() {
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                ScriptSig::new(Script::new(vec![])),
+                sequence,
+            )
+        };
+        let mut tx = BitcoinTransaction::new(2, vec![input(0xFFFFFFFE)], vec![], 0);
+        assert!(tx.is_final(0, 0));
+
+        tx.lock_time = 800_000;
+        assert!(!tx.is_final(800_000, 0));
+        assert!(tx.is_final(800_001, 0));
+
+        tx.lock_time = 500_000_000;
+        assert!(!tx.is_final(900_000, 500_000_000));
+        assert!(tx.is_final(0, 500_000_001));
+
+        tx.inputs = vec![input(0xFFFFFFFF), input(0xFFFFFFFF)];
+        assert!(tx.is_final(0, 0));
+    }
+
+    #[test]
+    fn test_rbf_signalling() {
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                ScriptSig::new(Script::new(vec![])),
+                sequence,
+            )
+        };
+        assert!(!input(0xFFFFFFFF).signals_rbf());
+        assert!(!input(0xFFFFFFFE).signals_rbf());
+        assert!(input(0xFFFFFFFD).signals_rbf());
+        assert!(input(0).signals_rbf());
+
+        let mut tx =
+            BitcoinTransaction::new(2, vec![input(0xFFFFFFFF), input(0xFFFFFFFE)], vec![], 0);
+        assert!(!tx.is_rbf());
+        tx.inputs.push(input(0xFFFFFFFD));
+        assert!(tx.is_rbf());
+    }
+
+    #[test]
+    fn test_tx_weight_and_vsize() {
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (legacy, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(legacy.stripped_size(), bytes.len());
+        assert_eq!(legacy.weight(), bytes.len() * 4);
+        assert_eq!(legacy.vsize(), bytes.len());
+
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (segwit, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(segwit.stripped_size(), 83);
+        assert_eq!(segwit.weight(), 442);
+        assert_eq!(segwit.vsize(), 111);
+    }
+
+    #[test]
+    fn test_decode_error_context() {
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+
+        // Cut inside the 33-byte pubkey, the second witness item.
+        let err = BitcoinTransaction::from_bytes_detailed(&bytes[..180]).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::new(155, "witness item", BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            err.to_string(),
+            "insufficient bytes to decode while decoding witness item at byte 155"
+        );
+
+        let err = BitcoinTransaction::from_bytes_detailed(&bytes[..bytes.len() - 2]).unwrap_err();
+        assert_eq!((err.offset, err.field), (bytes.len() - 4, "lock_time"));
+
+        let err = BitcoinTransaction::from_bytes_detailed(&bytes[..20]).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::new(6, "input count", BitcoinError::InvalidFormat)
+        );
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes[..180]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(BitcoinError::from(err), BitcoinError::InvalidFormat);
+    }
+
+    #[test]
+    fn test_from_bytes_partial() {
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (expected, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        // Cut inside the second output's script.
+        let (partial, error) = BitcoinTransaction::from_bytes_partial(&bytes[..bytes.len() - 10]);
+        assert_eq!(error, Some(BitcoinError::InsufficientBytes));
+        assert_eq!(partial.version, Some(1));
+        assert_eq!(partial.inputs, expected.inputs);
+        assert_eq!(partial.outputs, expected.outputs[..1]);
+        assert_eq!(partial.lock_time, None);
+
+        let (partial, error) = BitcoinTransaction::from_bytes_partial(&bytes[..2]);
+        assert_eq!(error, Some(BitcoinError::InsufficientBytes));
+        assert_eq!(partial, PartialTransaction::default());
+
+        let (partial, error) = BitcoinTransaction::from_bytes_partial(&bytes);
+        assert_eq!(error, None);
+        assert_eq!(partial.outputs, expected.outputs);
+        assert_eq!(partial.lock_time, Some(0));
+    }
+
+    #[test]
+    fn test_oversized_counts_rejected() {
+        let mut inputs = vec![0x01, 0x00, 0x00, 0x00];
+        inputs.extend_from_slice(&[0xFF; 9]);
+        inputs.extend_from_slice(&[0x00; 64]);
+        let err = BitcoinTransaction::from_bytes_detailed(&inputs).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::new(4, "input count", BitcoinError::InvalidFormat)
+        );
+
+        // One empty input, then an output count of 0xFFFF.
+        let mut outputs = vec![0x01, 0x00, 0x00, 0x00, 0x01];
+        outputs.extend_from_slice(&[0x00; 41]);
+        outputs.extend_from_slice(&[0xFD, 0xFF, 0xFF]);
+        outputs.extend_from_slice(&[0x00; 16]);
+        let err = BitcoinTransaction::from_bytes_detailed(&outputs).unwrap_err();
+        assert_eq!((err.offset, err.field), (46, "output count"));
+
+        // Replace the first witness item count of the SegWit tx with 2^32.
+        let mut witness = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let at = 83 - 4 + 2;
+        assert_eq!(witness[at], 0x02);
+        witness.splice(
+            at..at + 1,
+            [0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+        );
+        let err = BitcoinTransaction::from_bytes_detailed(&witness).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::new(at, "witness item count", BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_consensus_decode_from_reader() {
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let bytes = hex::decode(tx_hex).unwrap();
+            let (expected, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            let mut reader = std::io::Cursor::new(&bytes);
+            let decoded = BitcoinTransaction::consensus_decode(&mut reader).unwrap();
+            assert_eq!(decoded, expected);
+            assert_eq!(reader.position() as usize, bytes.len());
+        }
+
+        let cs = CompactSize::new(70_000);
+        let decoded = CompactSize::consensus_decode(&mut cs.to_bytes().as_slice()).unwrap();
+        assert_eq!(decoded, cs);
+
+        let outpoint = OutPoint::new(dummy_txid(9), 4);
+        let decoded = OutPoint::consensus_decode(&mut outpoint.to_bytes().as_slice()).unwrap();
+        assert_eq!(decoded, outpoint);
+
+        let input = TransactionInput::new(outpoint, ScriptSig::new(Script::new(vec![0xAA; 3])), 1);
+        let decoded = TransactionInput::consensus_decode(&mut input.to_bytes().as_slice()).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn test_consensus_encode_to_writer() {
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let bytes = hex::decode(tx_hex).unwrap();
+            let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            let mut writer = std::io::Cursor::new(Vec::new());
+            let written = tx.consensus_encode(&mut writer).unwrap();
+            assert_eq!(written, bytes.len());
+            assert_eq!(writer.into_inner(), bytes);
+        }
+
+        let script = Script::new(vec![0x00; 300]);
+        let mut buf = Vec::new();
+        assert_eq!(script.consensus_encode(&mut buf).unwrap(), 303);
+        assert_eq!(buf, script.to_bytes());
+    }
+
+    #[test]
+    fn test_consensus_decode_truncated() {
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let result = BitcoinTransaction::consensus_decode(&mut &bytes[..bytes.len() - 1]);
+        assert_eq!(result, Err(BitcoinError::InsufficientBytes));
+
+        let script = Script::new(vec![0x01; 10]).to_bytes();
+        let result = Script::consensus_decode(&mut &script[..5]);
+        assert_eq!(result, Err(BitcoinError::InsufficientBytes));
+    }
+
+    #[test]
+    fn test_txid_display_string() {
+        let txid = Txid(dummy_txid(0xAB));
+        let display = txid.to_display_string();
+        assert!(display.starts_with("ab00"));
+        assert_eq!(Txid::from_display_string(&display), Ok(txid));
+
+        let parsed = Txid::from_display_string(BLOCK_170_TXID).unwrap();
+        assert_eq!(parsed.to_display_string(), BLOCK_170_TXID);
+        assert_eq!(parsed.0[0], 0x16);
+
+        assert_eq!(
+            Txid::from_display_string("abcd"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Txid::from_display_string(&"zz".repeat(32)),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_txid_from_str() {
+        let txid: Txid = BLOCK_170_TXID.parse().unwrap();
+        assert_eq!(txid.0[0], 0x16);
+        assert_eq!(txid.0[31], 0xf4);
+        assert_eq!(txid, Txid::from_display_string(BLOCK_170_TXID).unwrap());
+
+        assert_eq!(
+            BLOCK_170_TXID[..62].parse::<Txid>(),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            "g".repeat(64).parse::<Txid>(),
+            Err(BitcoinError::InvalidFormat)
+        );
     }
 
     #[test]
     fn test_bitcoin_tx_json_serialization() {
         let input = TransactionInput::new(
             OutPoint::new(dummy_txid(0xAB), 3),
-            Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF]),
+            ScriptSig::new(Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF])),
             0xABCDEF01,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 999);
+        let output = TransactionOutput::new(1234, ScriptPubKey::new(Script::new(vec![0x6A])));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 999);
 
         let json = serde_json::to_string_pretty(&tx).unwrap();
         let parsed: BitcoinTransaction = serde_json::from_str(&json).unwrap();
@@ -101,13 +881,2122 @@ mod tests {
     fn test_bitcoin_transaction_display() {
         let input = TransactionInput::new(
             OutPoint::new(dummy_txid(0xCD), 7),
-            Script::new(vec![0x01, 0x02, 0x03]),
+            ScriptSig::new(Script::new(vec![0x01, 0x02, 0x03])),
             0xFFFFFFFF,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 0);
+        let output = TransactionOutput::new(5000, ScriptPubKey::new(Script::new(vec![0x51])));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 0);
         let output = format!("{}", tx);
         assert!(output.contains("Version: 1"));
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
+        assert!(output.contains("Outputs (1):"));
+        assert!(output.contains("Value: 5000"));
+    }
+
+    #[test]
+    fn test_block_header_roundtrip_and_hash() {
+        let genesis = hex::decode("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c").unwrap();
+        let (header, consumed) = BlockHeader::from_bytes(&genesis).unwrap();
+        assert_eq!(consumed, 80);
+        assert_eq!(header.version, 1);
+        assert_eq!(header.prev_blockhash, [0u8; 32]);
+        assert_eq!(header.time, 1231006505);
+        assert_eq!(header.bits, 0x1d00ffff);
+        assert_eq!(header.nonce, 2083236893);
+        assert_eq!(header.to_bytes(), genesis);
+
+        let mut hash = header.block_hash();
+        hash.reverse();
+        assert_eq!(
+            hex::encode(hash),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+
+        let decoded = BlockHeader::consensus_decode(&mut genesis.as_slice()).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(
+            BlockHeader::from_bytes(&genesis[..79]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_compute_merkle_root() {
+        let txid = |s: &str| s.parse::<Txid>().unwrap();
+
+        // Block 100000.
+        let txids = [
+            txid("8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87"),
+            txid("fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4"),
+            txid("6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4"),
+            txid("e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d"),
+        ];
+        assert_eq!(
+            compute_merkle_root(&txids).map(Txid),
+            Some(txid(
+                "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+            ))
+        );
+
+        // An odd level duplicates its last hash.
+        assert_eq!(
+            compute_merkle_root(&txids[..3]).map(Txid),
+            Some(txid(
+                "fa435470825de273081dcc706b25514c936fa6dc80ab965ce6970d68ddd0b553"
+            ))
+        );
+
+        assert_eq!(compute_merkle_root(&txids[..1]), Some(txids[0].0));
+        assert_eq!(compute_merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_all_concatenated() {
+        let mut bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        bytes.extend(hex::decode(SEGWIT_TX_HEX).unwrap());
+        bytes.extend(hex::decode(BLOCK_170_TX_HEX).unwrap());
+
+        let txs = BitcoinTransaction::parse_all(&bytes).unwrap();
+        let txids: Vec<String> = txs.iter().map(|tx| tx.txid().to_display_string()).collect();
+        assert_eq!(txids, [BLOCK_170_TXID, SEGWIT_TXID, BLOCK_170_TXID]);
+        assert_eq!(BitcoinTransaction::parse_all(&[]), Ok(vec![]));
+
+        bytes.extend_from_slice(&[0x01, 0x00]);
+        assert_eq!(
+            BitcoinTransaction::parse_all(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_exact_rejects_trailing_bytes() {
+        let mut bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (expected, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(BitcoinTransaction::from_bytes_exact(&bytes), Ok(expected));
+
+        bytes.push(0x00);
+        assert!(BitcoinTransaction::from_bytes(&bytes).is_ok());
+        assert_eq!(
+            BitcoinTransaction::from_bytes_exact(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_exact(&bytes[..10]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_serde_formats() {
+        for value in [0u64, 0xFC, 0xFD, 0xFFFF, 0x10000, 0xFFFFFFFF, 0x100000000] {
+            let cs = CompactSize::new(value);
+
+            let json = serde_json::to_string(&cs).unwrap();
+            assert_eq!(json, value.to_string());
+            assert_eq!(serde_json::from_str::<CompactSize>(&json).unwrap(), cs);
+
+            let binary = bincode::serialize(&cs).unwrap();
+            assert_eq!(binary, cs.to_bytes());
+            assert_eq!(bincode::deserialize::<CompactSize>(&binary).unwrap(), cs);
+        }
+
+        assert!(bincode::deserialize::<CompactSize>(&[0xFE, 0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_sequence_relative_lock_time() {
+        assert_eq!(Sequence::final_sequence(), Sequence(0xFFFFFFFF));
+        assert!(!Sequence::final_sequence().is_relative_lock_time());
+        assert_eq!(Sequence::final_sequence().to_relative_lock_time(), None);
+        assert_eq!(Sequence::enable_rbf().to_relative_lock_time(), None);
+
+        let blocks = Sequence::from_height(144);
+        assert_eq!(blocks, Sequence(144));
+        assert_eq!(
+            blocks.to_relative_lock_time(),
+            Some(RelativeLockTime::Blocks(144))
+        );
+
+        // Time-based locks set bit 22 and count 512-second units, rounding up.
+        let cases = [
+            (0, 0x00400000),
+            (1, 0x00400001),
+            (512, 0x00400001),
+            (513, 0x00400002),
+            (1024, 0x00400002),
+            (512 * 0xFFFF, 0x0040FFFF),
+        ];
+        for (seconds, encoded) in cases {
+            assert_eq!(Sequence::from_seconds(seconds), Ok(Sequence(encoded)));
+        }
+        assert_eq!(
+            Sequence::from_seconds(1024)
+                .unwrap()
+                .to_relative_lock_time(),
+            Some(RelativeLockTime::Seconds(1024))
+        );
+        assert_eq!(
+            Sequence::from_seconds(512 * 0xFFFF + 1),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let raw: u32 = Sequence::enable_rbf().into();
+        assert_eq!(raw, 0xFFFFFFFD);
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            ScriptSig::new(Script::new(vec![])),
+            Sequence::from_height(10),
+        );
+        assert!(input.signals_rbf());
+        assert_eq!(serde_json::to_value(input.sequence).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_wtxid() {
+        let (segwit, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(SEGWIT_TX_HEX).unwrap()).unwrap();
+        assert_eq!(segwit.wtxid().to_display_string(), SEGWIT_WTXID);
+        assert_ne!(segwit.wtxid(), segwit.txid());
+
+        let (legacy, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap()).unwrap();
+        assert_eq!(legacy.wtxid(), legacy.txid());
+        assert_eq!(legacy.wtxid().to_display_string(), BLOCK_170_TXID);
+    }
+
+    #[test]
+    fn test_script_builder_push_boundaries() {
+        let cases: [(usize, Vec<u8>); 5] = [
+            (0, vec![0x00]),
+            (75, vec![0x4B]),
+            (76, vec![OP_PUSHDATA1, 76]),
+            (255, vec![OP_PUSHDATA1, 0xFF]),
+            (256, vec![OP_PUSHDATA2, 0x00, 0x01]),
+        ];
+        for (len, prefix) in cases {
+            let data = vec![0xAB; len];
+            let script = ScriptBuilder::new().push_slice(&data).unwrap().build();
+            assert_eq!(&script[..prefix.len()], prefix.as_slice(), "{}", len);
+            assert_eq!(script.len(), prefix.len() + len);
+            let expected = if len == 0 {
+                Instruction::Op(OP_0)
+            } else {
+                Instruction::PushBytes(data)
+            };
+            assert_eq!(script.instructions().unwrap(), vec![expected]);
+        }
+
+        let data = vec![0xCD; 0x10000];
+        let script = ScriptBuilder::new().push_slice(&data).unwrap().build();
+        assert_eq!(&script[..5], &[OP_PUSHDATA4, 0x00, 0x00, 0x01, 0x00]);
+
+        let hash = hex::decode("89abcdefabbaabbaabbaabbaabbaabbaabbaabba").unwrap();
+        let p2pkh = ScriptBuilder::new()
+            .push_opcode(OP_DUP)
+            .push_opcode(OP_HASH160)
+            .push_slice(&hash)
+            .unwrap()
+            .push_opcode(OP_EQUALVERIFY)
+            .push_opcode(OP_CHECKSIG)
+            .build();
+        assert_eq!(p2pkh.script_type(), ScriptType::P2PKH);
+    }
+
+    #[test]
+    fn test_script_builder_push_int() {
+        let cases: [(i64, &str); 12] = [
+            (0, "00"),
+            (-1, "4f"),
+            (1, "51"),
+            (16, "60"),
+            (17, "0111"),
+            (-2, "0182"),
+            (127, "017f"),
+            (128, "028000"),
+            (-128, "028080"),
+            (255, "02ff00"),
+            (500_000, "0320a107"),
+            (i64::MIN, "09000000000000008080"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(
+                ScriptBuilder::new().push_int(n).build().to_hex(),
+                expected,
+                "{}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_transaction_accessors() {
+        let tx = TransactionBuilder::new()
+            .add_input(
+                OutPoint::new(dummy_txid(1), 0),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            )
+            .add_input(
+                OutPoint::new(dummy_txid(2), 3),
+                ScriptSig::new(Script::new(vec![])),
+                0,
+            )
+            .add_output(1000, ScriptPubKey::new(Script::new(vec![0x51])))
+            .build();
+
+        assert_eq!(tx.inputs().len(), 2);
+        assert_eq!(tx.outputs()[0].value, 1000);
+        let spent: Vec<OutPoint> = tx.iter_outpoints().cloned().collect();
+        assert_eq!(
+            spent,
+            vec![
+                OutPoint::new(dummy_txid(1), 0),
+                OutPoint::new(dummy_txid(2), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_standard_size_boundaries() {
+        // One input and one output with empty scripts encode to 60 bytes.
+        let tx_with = |script_sig_len, script_pubkey_len| {
+            TransactionBuilder::new()
+                .add_input(
+                    OutPoint::new(dummy_txid(1), 0),
+                    ScriptSig::new(Script::new(vec![0x00; script_sig_len])),
+                    0xFFFFFFFF,
+                )
+                .add_output(
+                    0,
+                    ScriptPubKey::new(Script::new(vec![0x00; script_pubkey_len])),
+                )
+                .build()
+        };
+
+        let smallest = tx_with(5, 0);
+        assert_eq!(smallest.stripped_size(), MIN_STANDARD_TX_NONWITNESS_SIZE);
+        assert!(smallest.is_standard_size());
+        assert!(!tx_with(4, 0).is_standard_size());
+
+        // A script of 65536 bytes or more takes a five-byte length prefix.
+        let largest = tx_with(5, 99_931);
+        assert_eq!(largest.weight(), MAX_STANDARD_TX_WEIGHT);
+        assert!(largest.is_standard_size());
+        assert!(!tx_with(5, 99_932).is_standard_size());
+    }
+
+    #[test]
+    fn test_serialized_len_matches_to_bytes() {
+        for value in [0u64, 0xFC, 0xFD, 0xFFFF, 0x10000, 0xFFFFFFFF, 0x100000000] {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.serialized_len(), cs.to_bytes().len());
+        }
+
+        let outpoint = OutPoint::new(dummy_txid(1), 0);
+        assert_eq!(outpoint.serialized_len(), outpoint.to_bytes().len());
+
+        for len in [0, 0xFC, 0xFD, 0x10000] {
+            let script = Script::new(vec![0x51; len]);
+            assert_eq!(script.serialized_len(), script.to_bytes().len());
+        }
+
+        let header = BlockHeader::from_bytes(&[0u8; 80]).unwrap().0;
+        assert_eq!(header.serialized_len(), header.to_bytes().len());
+
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let (tx, consumed) =
+                BitcoinTransaction::from_bytes(&hex::decode(tx_hex).unwrap()).unwrap();
+            assert_eq!(tx.serialized_len(), consumed);
+            assert_eq!(tx.serialized_len(), tx.to_bytes().len());
+            for input in &tx.inputs {
+                assert_eq!(input.serialized_len(), input.to_bytes().len());
+            }
+            for output in &tx.outputs {
+                assert_eq!(output.serialized_len(), output.to_bytes().len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_legacy_sighash() {
+        // Both transactions spend Satoshi-era P2PK outputs to this key.
+        let script_code = ScriptPubKey::new(Script::from_hex("410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac").unwrap());
+        let sighash = |tx: &BitcoinTransaction, index, sighash_type| {
+            hex::encode(legacy_sighash(tx, index, &script_code, sighash_type).unwrap())
+        };
+
+        let (tx, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap()).unwrap();
+        assert_eq!(
+            sighash(&tx, 0, SighashType::All),
+            "7a05c6145f10101e9d6325494245adf1297d80f8f38d4d576d57cdba220bcb19"
+        );
+        assert_eq!(
+            sighash(&tx, 0, SighashType::NonePlusAnyoneCanPay),
+            "e8bf86d8bee812482bc3befdff96675f2d6643e4d33db50dfb25bd0c37ec4ace"
+        );
+
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode("010000000321f75f3139a013f50f315b23b0c9a2b6eac31e2bec98e5891c924664889942260000000049483045022100cb2c6b346a978ab8c61b18b5e9397755cbd17d6eb2fe0083ef32e067fa6c785a02206ce44e613f31d9a6b0517e46f3db1576e9812cc98d159bfdaf759a5014081b5c01ffffffff79cda0945903627c3da1f85fc95d0b8ee3e76ae0cfdc9a65d09744b1f8fc85430000000049483045022047957cdd957cfd0becd642f6b84d82f49b6cb4c51a91f49246908af7c3cfdf4a022100e96b46621f1bffcf5ea5982f88cef651e9354f5791602369bf5a82a6cd61a62501fffffffffe09f5fe3ffbf5ee97a54eb5e5069e9da6b4856ee86fc52938c2f979b0f38e82000000004847304402204165be9a4cbab8049e1af9723b96199bfd3e85f44c6b4c0177e3962686b26073022028f638da23fc003760861ad481ead4099312c60030d4cb57820ce4d33812a5ce01ffffffff01009d966b01000000434104ea1feff861b51fe3f5f8a3b12d0f4712db80e919548a80839fc47c6a21e66d957e9c5d8cd108c7a2d2324bad71f9904ac0ae7336507d785b17a2c115e427a32fac00000000").unwrap()).unwrap();
+        let cases = [
+            (
+                0,
+                SighashType::All,
+                "7dd6d88dc1c7954d0e88a4ba01282138b1471bac73e086bc8763f3a233181ae6",
+            ),
+            (
+                1,
+                SighashType::All,
+                "6f9e1ddfc56839b3445f8ee0f8b95955d90fa17d37251a39c28c970c09e58d5c",
+            ),
+            (
+                1,
+                SighashType::None,
+                "70d149578634f8a3bdaf4a62e1257408ff35e6fa63fb970c6220e2eceb2fc181",
+            ),
+            (
+                0,
+                SighashType::Single,
+                "924d005b003e34c2cbe92385fdade184b5046a666da4b5861761bb2bc10db443",
+            ),
+            (
+                1,
+                SighashType::AllPlusAnyoneCanPay,
+                "0c046bd6538dce884a983b3a5b355e876cd6e419a4ed3d080df5cd597d26d832",
+            ),
+            (
+                2,
+                SighashType::NonePlusAnyoneCanPay,
+                "7d4291b16d53beab0240ade948bb90c70b090e2690700fb36fe7700c2f01cc98",
+            ),
+            // SIGHASH_SINGLE past the last output hashes to one.
+            (
+                1,
+                SighashType::Single,
+                "0100000000000000000000000000000000000000000000000000000000000000",
+            ),
+        ];
+        for (index, sighash_type, expected) in cases {
+            assert_eq!(
+                sighash(&tx, index, sighash_type),
+                expected,
+                "{} {:?}",
+                index,
+                sighash_type
+            );
+        }
+
+        assert_eq!(
+            legacy_sighash(&tx, 3, &script_code, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_segwit_sighash_bip143_vectors() {
+        let decode = |tx_hex: &str| {
+            BitcoinTransaction::from_bytes(&hex::decode(tx_hex).unwrap())
+                .unwrap()
+                .0
+        };
+
+        // Native P2WPKH, second input.
+        let tx = decode(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        );
+        let script_code = ScriptPubKey::new(
+            Script::from_hex("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap(),
+        );
+        assert_eq!(
+            hex::encode(
+                segwit_sighash(&tx, 1, &script_code, 600_000_000, SighashType::All).unwrap()
+            ),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+
+        // P2SH-P2WPKH.
+        let tx = decode(
+            "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000",
+        );
+        let script_code = ScriptPubKey::new(
+            Script::from_hex("76a91479091972186c449eb1ded22b78e40d009bdf008988ac").unwrap(),
+        );
+        assert_eq!(
+            hex::encode(
+                segwit_sighash(&tx, 0, &script_code, 1_000_000_000, SighashType::All).unwrap()
+            ),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+        );
+
+        // P2SH-P2WSH 6-of-6 multisig, signed with every sighash type.
+        let tx = decode(
+            "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000",
+        );
+        let script_code = ScriptPubKey::new(Script::from_hex("56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae").unwrap());
+        let cache = SegwitSighashCache::new(&tx);
+        let cases = [
+            (
+                SighashType::All,
+                "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            ),
+            (
+                SighashType::None,
+                "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            ),
+            (
+                SighashType::Single,
+                "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            ),
+            (
+                SighashType::AllPlusAnyoneCanPay,
+                "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            ),
+            (
+                SighashType::NonePlusAnyoneCanPay,
+                "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            ),
+            (
+                SighashType::SinglePlusAnyoneCanPay,
+                "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+            ),
+        ];
+        for (sighash_type, expected) in cases {
+            let sighash = cache
+                .sighash(0, &script_code, 987_654_321, sighash_type)
+                .unwrap();
+            assert_eq!(hex::encode(sighash), expected, "{:?}", sighash_type);
+        }
+
+        assert_eq!(
+            cache.sighash(1, &script_code, 0, SighashType::All),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_strip_magic() {
+        let mut message = hex::decode("f9beb4d9").unwrap();
+        message.extend_from_slice(b"version\0\0\0\0\0");
+        assert_eq!(
+            strip_magic(&message, Network::Mainnet),
+            Ok(&b"version\0\0\0\0\0"[..])
+        );
+        assert_eq!(
+            strip_magic(&message, Network::Testnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            strip_magic(&message[..3], Network::Mainnet),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        assert_eq!(Network::Testnet.magic(), [0x0B, 0x11, 0x09, 0x07]);
+        assert_eq!(Network::Regtest.magic(), [0xFA, 0xBF, 0xB5, 0xDA]);
+        assert_eq!(Network::Signet.magic(), [0x0A, 0x03, 0xCF, 0x40]);
+        assert_eq!(
+            strip_magic(&Network::Signet.magic(), Network::Signet),
+            Ok(&[][..])
+        );
+    }
+
+    #[test]
+    fn test_same_txid_ignores_witness() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(SEGWIT_TX_HEX).unwrap()).unwrap();
+        let mut malleated = tx.clone();
+        malleated.witnesses[0].push(&[]);
+
+        assert_ne!(tx, malleated);
+        assert!(tx.same_txid(&malleated));
+        assert_eq!(tx.txid(), malleated.txid());
+
+        malleated.lock_time += 1;
+        assert!(!tx.same_txid(&malleated));
+    }
+
+    #[test]
+    fn test_to_rpc_json() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(SEGWIT_TX_HEX).unwrap()).unwrap();
+        let json = tx.to_rpc_json();
+        assert_eq!(json["txid"], SEGWIT_TXID);
+        assert_eq!(json["hash"], SEGWIT_WTXID);
+        assert_eq!(json["version"], 2);
+        assert_eq!(json["size"], 193);
+        assert_eq!(json["vsize"], 111);
+        assert_eq!(json["weight"], 442);
+        assert_eq!(json["locktime"], 0);
+
+        let vin = &json["vin"][0];
+        assert_eq!(
+            vin["txid"],
+            "7cac3cf9a112cf04901a51d605058615d56ffe6d04b45270e89d1720ea955859"
+        );
+        assert_eq!(vin["vout"], 1);
+        assert_eq!(vin["scriptSig"]["hex"], "");
+        assert_eq!(vin["scriptSig"]["asm"], "");
+        assert_eq!(vin["txinwitness"].as_array().unwrap().len(), 2);
+        assert_eq!(vin["sequence"], 4294967295u32);
+
+        let vout = &json["vout"][0];
+        assert_eq!(vout["value"], 0.00506078);
+        assert_eq!(vout["n"], 0);
+        assert_eq!(
+            vout["scriptPubKey"]["hex"],
+            "a9140f3444e271620c736808aa7b33e370bd87cb5a0787"
+        );
+        assert_eq!(
+            vout["scriptPubKey"]["asm"],
+            "OP_HASH160 0f3444e271620c736808aa7b33e370bd87cb5a07 OP_EQUAL"
+        );
+
+        let coinbase = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                ScriptSig::new(Script::new(vec![0x04, 0xFF])),
+                0xFFFFFFFF,
+            )],
+            vec![],
+            0,
+        );
+        let vin = &coinbase.to_rpc_json()["vin"][0];
+        assert_eq!(vin["coinbase"], "04ff");
+        assert!(vin.get("txid").is_none());
+    }
+
+    #[test]
+    fn test_signable_transaction_prevouts() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap())
+            .unwrap()
+            .0;
+        let script_pubkey = ScriptPubKey::new(
+            Script::from_hex(
+                "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac",
+            )
+            .unwrap(),
+        );
+
+        let signable =
+            SignableTransaction::new(tx.clone(), vec![(script_pubkey.clone(), 5_000_000_000)])
+                .unwrap();
+        assert_eq!(signable.tx(), &tx);
+        assert_eq!(
+            signable.input_prevout(0),
+            Some((&script_pubkey, 5_000_000_000))
+        );
+        assert_eq!(signable.input_prevout(1), None);
+
+        assert_eq!(
+            SignableTransaction::new(tx.clone(), vec![]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            SignableTransaction::new(tx, vec![(script_pubkey.clone(), 1), (script_pubkey, 2)]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_try_from_slice() {
+        let raw_tx = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let tx = BitcoinTransaction::try_from(raw_tx.as_slice()).unwrap();
+        assert_eq!(tx.txid().to_display_string(), BLOCK_170_TXID);
+
+        let mut trailing = raw_tx.clone();
+        trailing.push(0x00);
+        assert_eq!(
+            BitcoinTransaction::try_from(trailing.as_slice()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        assert_eq!(
+            CompactSize::try_from(&[0xFD, 0x34, 0x12][..]),
+            Ok(CompactSize::new(0x1234))
+        );
+        assert_eq!(
+            CompactSize::try_from(&[0x01, 0x00][..]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let outpoint = tx.inputs[0].previous_output.clone();
+        assert_eq!(
+            OutPoint::try_from(outpoint.to_bytes().as_slice()),
+            Ok(outpoint)
+        );
+        assert_eq!(
+            OutPoint::try_from(&[0u8; 35][..]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        let script = Script::new(vec![0x51, 0x52]);
+        assert_eq!(Script::try_from(script.to_bytes().as_slice()), Ok(script));
+
+        let input = tx.inputs[0].clone();
+        let mut input_bytes = input.to_bytes();
+        assert_eq!(
+            TransactionInput::try_from(input_bytes.as_slice()),
+            Ok(input)
+        );
+        input_bytes.push(0xFF);
+        assert_eq!(
+            TransactionInput::try_from(input_bytes.as_slice()),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_script_address() {
+        let cases = [
+            (
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+                "mpXwg4jMtRhuSpVq4xS3HFHmCmWp9NyGKt",
+            ),
+            (
+                "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87",
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                "2N9hLwkSqr1cPQAPxbrGVUjxyjD11G2e1he",
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+            ),
+            (
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+                "tb1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqp3mvzv",
+            ),
+        ];
+        for (script_hex, mainnet, testnet) in cases {
+            let script = Script::from_hex(script_hex).unwrap();
+            assert_eq!(script.address(Network::Mainnet).as_deref(), Some(mainnet));
+            assert_eq!(script.address(Network::Testnet).as_deref(), Some(testnet));
+            assert_eq!(script.address(Network::Signet).as_deref(), Some(testnet));
+        }
+
+        let p2wpkh = Script::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        assert_eq!(
+            p2wpkh.address(Network::Regtest).as_deref(),
+            Some("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080")
+        );
+
+        let op_return = Script::from_hex("6a0548656c6c6f").unwrap();
+        assert_eq!(op_return.address(Network::Mainnet), None);
+        assert_eq!(Script::new(vec![0x51]).address(Network::Mainnet), None);
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let script = Script::from_hex("6a0548656c6c6f").unwrap();
+        assert_eq!(script.op_return_data(), Some(b"Hello".to_vec()));
+
+        // Only the first push is extracted from a multi-push OP_RETURN.
+        let multi_push = Script::from_hex("6a046f6d6e69024142").unwrap();
+        assert_eq!(multi_push.op_return_data(), Some(b"omni".to_vec()));
+
+        let mut pushdata1 = vec![0x6a, 0x4c, 80];
+        pushdata1.extend_from_slice(&[0xAB; 80]);
+        assert_eq!(
+            Script::new(pushdata1).op_return_data(),
+            Some(vec![0xAB; 80])
+        );
+
+        assert_eq!(Script::new(vec![0x6a]).op_return_data(), None);
+        assert_eq!(Script::new(vec![0x6a, 0x51]).op_return_data(), None);
+        assert_eq!(Script::new(vec![0x6a, 0x05, 0x01]).op_return_data(), None);
+        assert_eq!(
+            Script::from_hex("0548656c6c6f").unwrap().op_return_data(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_script_from_bytes_huge_length_prefix() {
+        for len in 1..=16 {
+            let bytes = vec![0xFF; len];
+            assert!(Script::from_bytes(&bytes).is_err(), "{} bytes", len);
+            assert!(ScriptRef::from_bytes(&bytes).is_err(), "{} bytes", len);
+        }
+
+        let mut near_max = vec![0xFF];
+        near_max.extend_from_slice(&(usize::MAX as u64).to_le_bytes());
+        assert_eq!(
+            Script::from_bytes(&near_max),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_signed_version() {
+        let mut tx = BitcoinTransaction::new(1, vec![], vec![], 0);
+        assert_eq!(tx.version_i32(), 1);
+        assert!(!tx.bip68_enabled());
+
+        tx.version = 2;
+        assert!(tx.bip68_enabled());
+
+        tx.version = 0xFFFFFFFF;
+        assert_eq!(tx.version_i32(), -1);
+        assert!(!tx.bip68_enabled());
+
+        tx.version = 0x80000000;
+        assert_eq!(tx.version_i32(), i32::MIN);
+        assert!(!tx.bip68_enabled());
+        assert_eq!(tx.to_bytes()[..4], [0x00, 0x00, 0x00, 0x80]);
+        assert_eq!(tx.to_rpc_json()["version"], -2147483648i64);
+    }
+
+    #[test]
+    fn test_with_input_script() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap())
+            .unwrap()
+            .0;
+        let script = Script::new(vec![0x51]);
+
+        let input = tx.inputs[0].with_script_sig(ScriptSig::new(script.clone()));
+        assert_eq!(input.script_sig.0, script);
+        assert_eq!(input.previous_output, tx.inputs[0].previous_output);
+        assert_eq!(input.sequence, tx.inputs[0].sequence);
+
+        let signed = tx
+            .with_input_script(0, ScriptSig::new(script.clone()))
+            .unwrap();
+        assert_eq!(signed.inputs[0], input);
+        assert_eq!(signed.outputs, tx.outputs);
+        assert_ne!(signed.txid(), tx.txid());
+        assert_ne!(tx.inputs[0].script_sig.0, script);
+
+        assert_eq!(
+            tx.with_input_script(1, ScriptSig::new(script)),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_conversions_and_ordering() {
+        assert_eq!(CompactSize::from(300u64), CompactSize::new(300));
+        assert_eq!(CompactSize::try_from(300usize), Ok(CompactSize::new(300)));
+        assert_eq!(CompactSize::new(300).value_as_usize(), Ok(300));
+
+        assert!(CompactSize::new(0xFC) < CompactSize::new(0xFD));
+        assert!(CompactSize::new(u64::MAX) > CompactSize::new(0x1_0000_0000));
+        let mut sizes = vec![
+            CompactSize::new(70000),
+            CompactSize::new(1),
+            CompactSize::new(300),
+        ];
+        sizes.sort();
+        assert_eq!(
+            sizes,
+            vec![
+                CompactSize::new(1),
+                CompactSize::new(300),
+                CompactSize::new(70000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_witness_encoding() {
+        let empty = Witness::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.to_bytes(), vec![0x00]);
+        assert_eq!(Witness::from_bytes(&[0x00]), Ok((Witness::new(), 1)));
+
+        let signature = vec![0x30; 72];
+        let mut single = Witness::new();
+        single.push(&signature);
+        let bytes = single.to_bytes();
+        assert_eq!(bytes.len(), 1 + 1 + 72);
+        assert_eq!(&bytes[..2], &[0x01, 0x48]);
+        assert_eq!(single.serialized_len(), bytes.len());
+        assert_eq!(Witness::from_bytes(&bytes), Ok((single, bytes.len())));
+
+        // P2WSH 2-of-2 multisig: dummy, two signatures, witness script.
+        let witness_script = {
+            let mut script = vec![0x52, 0x21];
+            script.extend_from_slice(&[0x02; 33]);
+            script.push(0x21);
+            script.extend_from_slice(&[0x03; 33]);
+            script.extend_from_slice(&[0x52, 0xAE]);
+            script
+        };
+        let mut p2wsh = Witness::new();
+        for element in [&[][..], &[0x30; 71], &[0x30; 72], &witness_script] {
+            p2wsh.push(element);
+        }
+        assert_eq!(p2wsh.len(), 4);
+        assert_eq!(p2wsh.get(0), Some(&[][..]));
+        assert_eq!(p2wsh.iter().last(), Some(witness_script.as_slice()));
+        let bytes = p2wsh.to_bytes();
+        assert_eq!(bytes[0], 0x04);
+        assert_eq!(
+            Witness::from_bytes(&bytes),
+            Ok((p2wsh.clone(), bytes.len()))
+        );
+        assert_eq!(Witness::consensus_decode(&mut bytes.as_slice()), Ok(p2wsh));
+
+        assert_eq!(
+            Witness::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_parse_p2pkh_sig() {
+        // First input of mainnet tx 0543411b..., a P2PKH spend.
+        let signature = "304402206fbcec8d2d2e740d824d3d36cc345b37d9f65d665a99f5bd5c9e8d42270a03a8022013959632492332200c2908459547bf8dbf97c65ab1a28dec377d6f1d41d3d63e01";
+        let pubkey = "03d7279dfb90ce17fe139ba60a7c41ddf605b25e1c07a4ddcb9dfef4e7d6710f48";
+        let script_sig = Script::from_hex(&format!("47{}21{}", signature, pubkey)).unwrap();
+
+        let (parsed_signature, parsed_pubkey) = script_sig.parse_p2pkh_sig().unwrap();
+        assert_eq!(hex::encode(parsed_signature), signature);
+        assert_eq!(hex::encode(parsed_pubkey), pubkey);
+
+        // P2PK scriptSig from block 170: a signature alone.
+        let p2pk = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap())
+            .unwrap()
+            .0
+            .inputs[0]
+            .script_sig
+            .clone();
+        assert_eq!(p2pk.parse_p2pkh_sig(), None);
+
+        let truncated = Script::from_hex(&format!("47{}21{}", signature, &pubkey[..64])).unwrap();
+        assert_eq!(truncated.parse_p2pkh_sig(), None);
+        let bad_pubkey = Script::from_hex(&format!("47{}0401020304", signature)).unwrap();
+        assert_eq!(bad_pubkey.parse_p2pkh_sig(), None);
+        let extra_op = Script::from_hex(&format!("47{}21{}51", signature, pubkey)).unwrap();
+        assert_eq!(extra_op.parse_p2pkh_sig(), None);
+    }
+
+    #[test]
+    fn test_from_bytes_limited() {
+        let raw_tx = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes_limited(&raw_tx, raw_tx.len()).unwrap();
+        assert_eq!(consumed, raw_tx.len());
+        assert_eq!(tx.txid().to_display_string(), BLOCK_170_TXID);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_limited(&raw_tx, raw_tx.len() - 1),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_limited(&raw_tx[..raw_tx.len() - 1], 1000),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // Claims 0xFFFFFFFF inputs; the count is rejected against the limit,
+        // not the much larger buffer, so nothing is reserved for it.
+        let mut crafted = vec![0x01, 0x00, 0x00, 0x00, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF];
+        crafted.resize(1_000_000, 0x00);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_limited(&crafted, 1000),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&crafted),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_txid_byte_conversions() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0xAA;
+        bytes[31] = 0x01;
+
+        let txid = Txid::from(bytes);
+        assert_eq!(txid, Txid(bytes));
+        assert_eq!(txid.as_ref(), &bytes[..]);
+        assert_eq!(<[u8; 32]>::from(txid), bytes);
+    }
+
+    #[test]
+    fn test_script_new_checked() {
+        let at_limit = vec![0x51; MAX_SCRIPT_SIZE];
+        assert_eq!(
+            Script::new_checked(at_limit.clone(), MAX_SCRIPT_SIZE),
+            Ok(Script::new(at_limit))
+        );
+        assert_eq!(
+            Script::new_checked(vec![0x51; MAX_SCRIPT_SIZE + 1], MAX_SCRIPT_SIZE),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Script::new_checked(vec![0xAB; 521], MAX_SCRIPT_ELEMENT_SIZE),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_script_display() {
+        let p2pkh = Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        assert_eq!(
+            p2pkh.to_string(),
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+
+        let malformed = Script::from_hex("76a914aabb").unwrap();
+        assert_eq!(malformed.to_string(), "76a914aabb");
+    }
+
+    #[test]
+    fn test_from_bytes_with_spans() {
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let bytes = hex::decode(tx_hex).unwrap();
+            let (tx, spans) = BitcoinTransaction::from_bytes_with_spans(&bytes).unwrap();
+            assert_eq!(spans.script_sigs.len(), tx.inputs.len());
+            assert_eq!(spans.script_pubkeys.len(), tx.outputs.len());
+            for (input, span) in tx.inputs.iter().zip(&spans.script_sigs) {
+                assert_eq!(&bytes[span.range()], input.script_sig.as_slice());
+            }
+            for (output, span) in tx.outputs.iter().zip(&spans.script_pubkeys) {
+                assert_eq!(&bytes[span.range()], output.script_pubkey.as_slice());
+            }
+        }
+
+        let bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (_, spans) = BitcoinTransaction::from_bytes_with_spans(&bytes).unwrap();
+        assert_eq!(
+            spans.script_sigs[0],
+            ByteSpan {
+                offset: 42,
+                len: 72
+            }
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_spans(&bytes[..50]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // A non-minimal length prefix moves the script along with it.
+        let mut padded = bytes.clone();
+        padded.splice(41..42, [0xfd, 0x48, 0x00]);
+        let (tx, spans) = BitcoinTransaction::from_bytes_with_spans(&padded).unwrap();
+        assert_eq!(spans.script_sigs[0].offset, 44);
+        assert_eq!(
+            &padded[spans.script_sigs[0].range()],
+            tx.inputs[0].script_sig.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_txid_from_display_bytes() {
+        let display: [u8; 32] = hex::decode(BLOCK_170_TXID).unwrap().try_into().unwrap();
+        let txid = Txid::from_display_bytes(display);
+        assert_eq!(txid.to_display_string(), BLOCK_170_TXID);
+        assert_eq!(txid, Txid::from_display_string(BLOCK_170_TXID).unwrap());
+        assert_eq!(txid.0[0], display[31]);
+    }
+
+    #[test]
+    fn test_sighash_type_flags() {
+        let cases = [
+            (0x01, SighashType::All),
+            (0x02, SighashType::None),
+            (0x03, SighashType::Single),
+            (0x81, SighashType::AllPlusAnyoneCanPay),
+            (0x82, SighashType::NonePlusAnyoneCanPay),
+            (0x83, SighashType::SinglePlusAnyoneCanPay),
+        ];
+        for (n, sighash_type) in cases {
+            assert_eq!(SighashType::from_u32(n), sighash_type);
+            assert_eq!(sighash_type.to_u32(), n);
+        }
+
+        // Other values keep their raw form, which is what gets hashed.
+        for n in [0x00, 0x04, 0x41, 0x84, 0x22] {
+            assert_eq!(SighashType::from_u32(n), SighashType::NonStandard(n));
+            assert_eq!(SighashType::from_u32(n).to_u32(), n);
+        }
+    }
+
+    #[test]
+    fn test_sighash_appends_raw_hash_type() {
+        let script_code = ScriptPubKey::new(Script::from_hex("410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac").unwrap());
+        let (tx, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap()).unwrap();
+        let sighash = |raw| {
+            hex::encode(legacy_sighash(&tx, 0, &script_code, SighashType::from_u32(raw)).unwrap())
+        };
+
+        // Non-standard types sign like their base type but hash the raw value.
+        let all = sighash(0x01);
+        assert_ne!(sighash(0x00), all);
+        let cases = [
+            (
+                0x00,
+                "90976290568a3995d278812bc2e2427ce1892d7eeb8f20e88cb4ef1cb6f63f6c",
+            ),
+            (
+                0x04,
+                "6ba28b1e6767ecb1e8a04d8eae662e065b06eb3503105014ef7cc09acca6ffab",
+            ),
+            (
+                0x41,
+                "f4e98a0a21093ae2d03e203aa71decb9eabbd156adc3ce45d72a56f2eda1dcef",
+            ),
+            (
+                0x84,
+                "861c3d1c4e8f77e0214afb3f38f3635439e98dc0a895684b4301af1ab4a4a17c",
+            ),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(sighash(raw), expected, "{:#x}", raw);
+        }
+
+        let segwit = |raw| segwit_sighash(&tx, 0, &script_code, 50_000, SighashType::from_u32(raw));
+        assert_ne!(segwit(0x00), segwit(0x01));
+        assert_ne!(segwit(0x41), segwit(0x01));
+    }
+
+    #[test]
+    fn test_output_is_dust() {
+        let p2pkh = Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        let p2wpkh = Script::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+
+        assert!(
+            TransactionOutput::new(545, ScriptPubKey::new(p2pkh.clone()))
+                .is_dust(DUST_RELAY_TX_FEE)
+        );
+        assert!(
+            !TransactionOutput::new(546, ScriptPubKey::new(p2pkh.clone()))
+                .is_dust(DUST_RELAY_TX_FEE)
+        );
+        assert!(
+            TransactionOutput::new(293, ScriptPubKey::new(p2wpkh.clone()))
+                .is_dust(DUST_RELAY_TX_FEE)
+        );
+        assert!(!TransactionOutput::new(294, ScriptPubKey::new(p2wpkh)).is_dust(DUST_RELAY_TX_FEE));
+        assert!(!TransactionOutput::new(0, ScriptPubKey::new(p2pkh.clone())).is_dust(0));
+
+        // 182 bytes at 1001 sat/kvB is 182.182 sat, which Core rounds up.
+        assert!(TransactionOutput::new(182, ScriptPubKey::new(p2pkh.clone())).is_dust(1001));
+        assert!(!TransactionOutput::new(183, ScriptPubKey::new(p2pkh.clone())).is_dust(1001));
+        // Any non-zero rate costs at least one satoshi.
+        assert!(TransactionOutput::new(0, ScriptPubKey::new(p2pkh.clone())).is_dust(1));
+        assert!(!TransactionOutput::new(1, ScriptPubKey::new(p2pkh.clone())).is_dust(1));
+        // A huge rate saturates rather than overflowing.
+        assert!(
+            TransactionOutput::new(21_000_000 * 100_000_000, ScriptPubKey::new(p2pkh.clone()))
+                .is_dust(u64::MAX)
+        );
+        assert!(!TransactionOutput::new(u64::MAX, ScriptPubKey::new(p2pkh)).is_dust(u64::MAX));
+
+        let op_return = Script::from_hex("6a0548656c6c6f").unwrap();
+        assert!(
+            !TransactionOutput::new(0, ScriptPubKey::new(op_return)).is_dust(DUST_RELAY_TX_FEE)
+        );
+    }
+
+    #[test]
+    fn test_strip_witness_and_legacy_bytes() {
+        use sha2::{Digest, Sha256};
+
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        let legacy = tx.to_bytes_legacy();
+        assert_eq!(legacy, tx.strip_witness().to_bytes());
+        assert_ne!(legacy, bytes);
+        assert_eq!(legacy.len(), tx.stripped_size());
+        assert_eq!(&legacy[..4], &bytes[..4]);
+        assert_eq!(&legacy[legacy.len() - 4..], &bytes[bytes.len() - 4..]);
+
+        let mut preimage_hash: [u8; 32] = Sha256::digest(Sha256::digest(&legacy)).into();
+        preimage_hash.reverse();
+        assert_eq!(hex::encode(preimage_hash), SEGWIT_TXID);
+
+        let stripped = tx.strip_witness();
+        assert!(stripped.witnesses.is_empty());
+        assert_eq!(stripped.txid(), tx.txid());
+        assert_eq!(stripped.wtxid(), tx.txid());
+        assert_eq!(stripped.inputs, tx.inputs);
+    }
+
+    #[test]
+    fn test_decode_vec() {
+        let parse_u16 = |bytes: &[u8]| match bytes {
+            [lo, hi, ..] => Ok((u16::from_le_bytes([*lo, *hi]), 2)),
+            _ => Err(BitcoinError::InsufficientBytes),
+        };
+
+        let bytes = [0x03, 0x01, 0x00, 0x02, 0x00, 0x34, 0x12, 0xFF];
+        assert_eq!(decode_vec(&bytes, parse_u16), Ok((vec![1, 2, 0x1234], 7)));
+        assert_eq!(decode_vec(&[0x00], parse_u16), Ok((vec![], 1)));
+        assert_eq!(
+            decode_vec(&bytes[..6], parse_u16),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            decode_vec(&[], parse_u16),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // A count larger than the remaining bytes fails before parsing.
+        let huge = [0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00];
+        assert_eq!(
+            decode_vec(&huge, parse_u16),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let tx = BitcoinTransaction::from_hex(tx_hex).unwrap();
+            assert_eq!(tx.to_hex(), tx_hex);
+
+            let input = &tx.inputs[0];
+            assert_eq!(
+                TransactionInput::from_hex(&input.to_hex()).as_ref(),
+                Ok(input)
+            );
+            let outpoint = &input.previous_output;
+            assert_eq!(
+                OutPoint::from_hex(&outpoint.to_hex()).as_ref(),
+                Ok(outpoint)
+            );
+            let script = &input.script_sig.0;
+            assert_eq!(Script::from_hex(&script.to_hex()).as_ref(), Ok(script));
+        }
+
+        assert_eq!(
+            BitcoinTransaction::from_hex("zz"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_hex(&format!("{}00", BLOCK_170_TX_HEX)),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            OutPoint::from_hex("00"),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_inventory_roundtrip() {
+        let mut hash = [0u8; 32];
+        hash[0] = 0x11;
+        hash[31] = 0xEE;
+        let cases = [
+            (Inventory::Tx(Txid(hash)), [0x01, 0x00, 0x00, 0x00]),
+            (Inventory::Block(BlockHash(hash)), [0x02, 0x00, 0x00, 0x00]),
+            (Inventory::WitnessTx(Txid(hash)), [0x01, 0x00, 0x00, 0x40]),
+            (
+                Inventory::WitnessBlock(BlockHash(hash)),
+                [0x02, 0x00, 0x00, 0x40],
+            ),
+        ];
+        for (inventory, type_code) in cases {
+            let bytes = inventory.to_bytes();
+            assert_eq!(bytes.len(), Inventory::SIZE);
+            assert_eq!(bytes[..4], type_code);
+            assert_eq!(bytes[4..], hash);
+            assert_eq!(
+                Inventory::consensus_decode(&mut bytes.as_slice()).as_ref(),
+                Ok(&inventory)
+            );
+            assert_eq!(Inventory::from_bytes(&bytes), Ok((inventory, 36)));
+        }
+
+        let mut unknown = Inventory::Tx(Txid(hash)).to_bytes();
+        unknown[0] = 0x03;
+        assert_eq!(
+            Inventory::from_bytes(&unknown),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Inventory::from_bytes(&unknown[..35]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_tx_input_from_bytes_boundaries() {
+        let input = TransactionInput::new(
+            OutPoint::new([0x11; 32], 0),
+            ScriptSig::new(Script::new(vec![0x51; 3])),
+            0xFFFFFFFF,
+        );
+        let bytes = input.to_bytes();
+        assert_eq!(bytes.len(), 36 + 1 + 3 + 4);
+
+        // Every truncation, including one ending exactly after the script,
+        // fails cleanly instead of slicing out of range.
+        for len in 0..bytes.len() {
+            assert_eq!(
+                TransactionInput::from_bytes(&bytes[..len]),
+                Err(BitcoinError::InsufficientBytes),
+                "{} bytes",
+                len
+            );
+        }
+        assert_eq!(TransactionInput::from_bytes(&bytes), Ok((input, 44)));
+
+        // A script length prefix claiming nearly all of memory.
+        let mut huge = vec![0x22; 36];
+        huge.push(0xFF);
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(TransactionInput::from_bytes(&huge).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let tx = BitcoinTransaction::from_hex(BLOCK_170_TX_HEX).unwrap();
+        assert_eq!(
+            tx.summary(),
+            "txid=f4184fc5 v=1 in=1 out=2 locktime=0 rbf=false"
+        );
+
+        let mut replaceable = tx.clone();
+        replaceable.version = 2;
+        replaceable.inputs[0].sequence = Sequence::enable_rbf();
+        replaceable.lock_time = 800_000;
+        assert!(
+            replaceable
+                .summary()
+                .ends_with(" v=2 in=1 out=2 locktime=800000 rbf=true")
+        );
+    }
+
+    #[test]
+    fn test_coinbase_spendable_height() {
+        let coinbase = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                ScriptSig::new(Script::new(vec![0x03, 0x40, 0x0D, 0x03])),
+                0xFFFFFFFF,
+            )],
+            vec![TransactionOutput::new(
+                625_000_000,
+                ScriptPubKey::new(Script::new(vec![0x51])),
+            )],
+            0,
+        );
+        assert_eq!(coinbase.coinbase_spendable_height(200_000), Some(200_100));
+        assert_eq!(coinbase.coinbase_spendable_height(u32::MAX), None);
+
+        let spend = BitcoinTransaction::from_hex(BLOCK_170_TX_HEX).unwrap();
+        assert_eq!(spend.coinbase_spendable_height(170), None);
+    }
+
+    #[test]
+    fn test_created_utxos() {
+        let tx = BitcoinTransaction::from_hex(BLOCK_170_TX_HEX).unwrap();
+        let utxos = tx.created_utxos();
+        assert_eq!(utxos.len(), 2);
+        for (vout, (outpoint, output)) in utxos.iter().enumerate() {
+            assert_eq!(outpoint.txid, tx.txid());
+            assert_eq!(outpoint.vout, vout as u32);
+            assert_eq!(*output, &tx.outputs[vout]);
+        }
+        assert_eq!(utxos[0].1.value, 1_000_000_000);
+        assert_eq!(utxos[1].1.value, 4_000_000_000);
+    }
+
+    #[test]
+    fn test_zero_after_version_is_segwit_marker() {
+        let bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(tx.inputs.len(), 1);
+        assert_eq!(tx.witnesses.len(), 1);
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut bytes.as_slice()),
+            Ok(tx)
+        );
+
+        // Read as legacy, this would be zero inputs and zero outputs.
+        let ambiguous = hex::decode("01000000000000000000").unwrap();
+        let err = BitcoinTransaction::from_bytes_detailed(&ambiguous).unwrap_err();
+        assert_eq!(
+            err,
+            DecodeError::new(5, "segwit flag", BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut ambiguous.as_slice()),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&ambiguous[..5]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_borrowed_input_matches_owned() {
+        let tx = BitcoinTransaction::from_hex(BLOCK_170_TX_HEX).unwrap();
+        let bytes = tx.inputs[0].to_bytes();
+
+        let (borrowed, consumed) = TransactionInputRef::from_bytes(&bytes).unwrap();
+        let (owned, owned_consumed) = TransactionInput::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, owned_consumed);
+        assert_eq!(borrowed.script_sig.0, owned.script_sig.as_slice());
+        assert_eq!(borrowed.script_sig.0.as_ptr(), bytes[37..].as_ptr());
+        assert_eq!(borrowed.previous_output, owned.previous_output);
+        assert_eq!(borrowed.sequence, owned.sequence);
+        assert_eq!(borrowed.to_owned(), owned);
+
+        assert_eq!(
+            TransactionInputRef::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_sort_bip69() {
+        // Display-order txids 01..00 < 02..00 even though the internal
+        // byte order would put the second first.
+        let mut low = [0u8; 32];
+        low[31] = 0x01;
+        low[0] = 0xFF;
+        let mut high = [0u8; 32];
+        high[31] = 0x02;
+        let input = |txid: [u8; 32], vout| {
+            TransactionInput::new(
+                OutPoint::new(txid, vout),
+                ScriptSig::new(Script::new(vec![])),
+                0xFFFFFFFF,
+            )
+        };
+        let output = |value, script: &[u8]| {
+            TransactionOutput::new(value, ScriptPubKey::new(Script::new(script.to_vec())))
+        };
+
+        let mut tx = BitcoinTransaction::new(
+            2,
+            vec![input(high, 0), input(low, 1), input(low, 0)],
+            vec![
+                output(2000, &[0x51]),
+                output(1000, &[0x52]),
+                output(1000, &[0x51]),
+            ],
+            0,
+        );
+        tx.witnesses = vec![
+            Witness::from(vec![vec![0xAA]]),
+            Witness::from(vec![vec![0xBB]]),
+            Witness::from(vec![vec![0xCC]]),
+        ];
+        assert!(!tx.is_bip69_sorted());
+
+        tx.sort_bip69();
+        assert!(tx.is_bip69_sorted());
+        assert_eq!(
+            tx.inputs,
+            vec![input(low, 0), input(low, 1), input(high, 0)]
+        );
+        assert_eq!(
+            tx.witnesses,
+            vec![
+                Witness::from(vec![vec![0xCC]]),
+                Witness::from(vec![vec![0xBB]]),
+                Witness::from(vec![vec![0xAA]]),
+            ]
+        );
+        assert_eq!(
+            tx.outputs,
+            vec![
+                output(1000, &[0x51]),
+                output(1000, &[0x52]),
+                output(2000, &[0x51])
+            ]
+        );
+
+        let sorted = tx.clone();
+        tx.sort_bip69();
+        assert_eq!(tx, sorted);
+    }
+
+    #[test]
+    fn test_script_is_equivalent() {
+        let p2pkh = Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        let padded =
+            Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac61b0b9").unwrap();
+        assert!(p2pkh.is_equivalent(&padded));
+        assert!(padded.is_equivalent(&p2pkh));
+        assert!(p2pkh.is_equivalent(&p2pkh));
+
+        // OP_CHECKLOCKTIMEVERIFY (OP_NOP2) is not padding.
+        let with_cltv =
+            Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888acb1").unwrap();
+        assert!(!p2pkh.is_equivalent(&with_cltv));
+
+        // A trailing 0x61 inside push data is not an opcode.
+        let push = Script::from_hex("0161").unwrap();
+        let push_padded = Script::from_hex("016161").unwrap();
+        assert!(push.is_equivalent(&push_padded));
+        assert!(!push.is_equivalent(&Script::new(vec![])));
+
+        // Only trailing no-ops are dropped.
+        assert!(
+            !Script::from_hex("6151")
+                .unwrap()
+                .is_equivalent(&Script::from_hex("51").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_decode_block_transactions() {
+        let body = hex::decode(format!("02{}{}", BLOCK_170_TX_HEX, SEGWIT_TX_HEX)).unwrap();
+        let (txs, consumed) = decode_block_transactions(&body).unwrap();
+        assert_eq!(consumed, body.len());
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].txid().to_display_string(), BLOCK_170_TXID);
+        assert_eq!(txs[1].txid().to_display_string(), SEGWIT_TXID);
+
+        assert_eq!(
+            decode_block_transactions(&body[..body.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // 3 transactions cannot fit in what follows the count.
+        let mut short = body.clone();
+        short[0] = 0x03;
+        short.truncate(1 + 100);
+        assert_eq!(
+            decode_block_transactions(&short),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_block_roundtrip_and_merkle_root() {
+        // Mainnet block 00000000b0c5a240...: a coinbase and tx a3b0e9e7....
+        let bytes = hex::decode(
+            "010000004ddccd549d28f385ab457e98d1b11ce80bfea2c5ab93015ade4973e400000000bf4473e53794beae34e64fccc471dace6ae544180816f89591894e0f417a914cd74d6e49ffff001d323b3a7b0201000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0804ffff001d026e04ffffffff0100f2052a0100000043410446ef0102d1ec5240f0d061a4246c1bdef63fc3dbab7733052fbbf0ecd8f41fc26bf049ebb4f9527f374280259e7cfa99c48b0e3f39c51347a19a5819651503a5ac00000000010000000321f75f3139a013f50f315b23b0c9a2b6eac31e2bec98e5891c924664889942260000000049483045022100cb2c6b346a978ab8c61b18b5e9397755cbd17d6eb2fe0083ef32e067fa6c785a02206ce44e613f31d9a6b0517e46f3db1576e9812cc98d159bfdaf759a5014081b5c01ffffffff79cda0945903627c3da1f85fc95d0b8ee3e76ae0cfdc9a65d09744b1f8fc85430000000049483045022047957cdd957cfd0becd642f6b84d82f49b6cb4c51a91f49246908af7c3cfdf4a022100e96b46621f1bffcf5ea5982f88cef651e9354f5791602369bf5a82a6cd61a62501fffffffffe09f5fe3ffbf5ee97a54eb5e5069e9da6b4856ee86fc52938c2f979b0f38e82000000004847304402204165be9a4cbab8049e1af9723b96199bfd3e85f44c6b4c0177e3962686b26073022028f638da23fc003760861ad481ead4099312c60030d4cb57820ce4d33812a5ce01ffffffff01009d966b01000000434104ea1feff861b51fe3f5f8a3b12d0f4712db80e919548a80839fc47c6a21e66d957e9c5d8cd108c7a2d2324bad71f9904ac0ae7336507d785b17a2c115e427a32fac00000000",
+        )
+        .unwrap();
+
+        let (block, consumed) = Block::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(block.transactions.len(), 2);
+        assert!(block.transactions[0].is_coinbase());
+        assert_eq!(
+            block.transactions[1].txid().to_display_string(),
+            "a3b0e9e7cddbbe78270fa4182a7675ff00b92872d8df7d14265a2b1e379a9d33"
+        );
+        assert_eq!(
+            BlockHash(block.header.block_hash()).to_display_string(),
+            "00000000b0c5a240b2a61d2e75692224efd4cbecdf6eaf4cc2cf477ca7c270e7"
+        );
+        assert!(block.verify_merkle_root());
+        assert_eq!(block.to_bytes(), bytes);
+        assert_eq!(
+            Block::consensus_decode(&mut bytes.as_slice()),
+            Ok(block.clone())
+        );
+
+        let mut tampered = block.clone();
+        tampered.transactions.swap(0, 1);
+        assert!(!tampered.verify_merkle_root());
+        tampered.transactions.clear();
+        assert!(!tampered.verify_merkle_root());
+
+        assert_eq!(
+            Block::from_bytes(&bytes[..bytes.len() - 4]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_fee_rate() {
+        let tx = BitcoinTransaction::from_hex(SEGWIT_TX_HEX).unwrap();
+        assert_eq!(tx.vsize(), 111);
+        let spent = tx.total_output_value().unwrap() + 2_220;
+        assert_eq!(tx.fee(&[spent]), Ok(2_220));
+        assert_eq!(tx.fee_rate(&[spent]), Ok(20.0));
+
+        let odd = tx.total_output_value().unwrap() + 1_000;
+        let rate = tx.fee_rate(&[odd]).unwrap();
+        assert!((rate - 1_000.0 / 111.0).abs() < 1e-9);
+
+        assert_eq!(tx.fee_rate(&[]), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.fee_rate(&[0]), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_pays_to_hash() {
+        let pubkey_hash: [u8; 20] = hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let script_hash: [u8; 20] = hex::decode("b472a266d0bd89c13706a4132ccfb16f7c3b9fcb")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let p2pkh = Script::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        let p2sh = Script::from_hex("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87").unwrap();
+
+        assert!(p2pkh.pays_to_pubkey_hash(&pubkey_hash));
+        assert!(!p2pkh.pays_to_pubkey_hash(&script_hash));
+        assert!(!p2pkh.pays_to_script_hash(&pubkey_hash));
+
+        assert!(p2sh.pays_to_script_hash(&script_hash));
+        assert!(!p2sh.pays_to_script_hash(&pubkey_hash));
+        assert!(!p2sh.pays_to_pubkey_hash(&script_hash));
+
+        // A P2WPKH program with the same hash is not P2PKH.
+        let p2wpkh = Script::from_hex("001462e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert!(!p2wpkh.pays_to_pubkey_hash(&pubkey_hash));
+    }
+
+    #[test]
+    fn test_bip143_preimages() {
+        let tx_bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+        let n = tx.inputs.len();
+
+        let prevouts = tx.serialize_prevouts();
+        assert_eq!(prevouts.len(), 36 * n);
+        assert_eq!(
+            &prevouts[..36],
+            tx.inputs[0].previous_output.to_bytes().as_slice()
+        );
+
+        let sequences = tx.serialize_sequences();
+        assert_eq!(sequences.len(), 4 * n);
+        assert_eq!(&sequences[..4], &tx.inputs[0].sequence.0.to_le_bytes());
+
+        let outputs = tx.serialize_outputs();
+        let expected: usize = tx.outputs.iter().map(|o| o.to_bytes().len()).sum();
+        assert_eq!(outputs.len(), expected);
+
+        let empty = BitcoinTransaction::new(2, vec![], vec![], 0);
+        assert!(empty.serialize_prevouts().is_empty());
+        assert!(empty.serialize_sequences().is_empty());
+        assert!(empty.serialize_outputs().is_empty());
+    }
+
+    #[test]
+    fn test_varint_helpers_match_compact_size() {
+        for value in [
+            0u64,
+            0xFC,
+            0xFD,
+            0xFFFF,
+            0x10000,
+            0xFFFF_FFFF,
+            0x1_0000_0000,
+            u64::MAX,
+        ] {
+            let size = CompactSize::new(value);
+            let bytes = encode_varint(value);
+            assert_eq!(bytes, size.to_bytes());
+            assert_eq!(decode_varint(&bytes), Ok((value, size.encoded_len())));
+
+            let mut written = Vec::new();
+            assert_eq!(write_varint(&mut written, value), Ok(bytes.len()));
+            assert_eq!(written, bytes);
+        }
+        assert_eq!(
+            decode_varint(&[0xFD, 0x01]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_display() {
+        assert_eq!(CompactSize::new(42).to_string(), "42 (1 byte)");
+        assert_eq!(CompactSize::new(0xFD).to_string(), "253 (3 bytes)");
+        assert_eq!(CompactSize::new(70000).to_string(), "70000 (5 bytes)");
+        assert_eq!(
+            CompactSize::new(0x1_0000_0000).to_string(),
+            "4294967296 (9 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_set_sequence_and_locktime() {
+        let tx_bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (mut tx, _) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+        let prevout = tx.inputs[0].previous_output.clone();
+
+        assert_eq!(tx.set_sequence(0, 0xFFFF_FFFD), Ok(()));
+        assert_eq!(tx.inputs[0].sequence, Sequence(0xFFFF_FFFD));
+        assert_eq!(tx.inputs[0].previous_output, prevout);
+        assert!(tx.is_rbf());
+
+        assert_eq!(
+            tx.set_sequence(tx.inputs.len(), 0),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        tx.set_locktime(840_000);
+        assert_eq!(tx.lock_time, 840_000);
+    }
+
+    #[test]
+    fn test_spends() {
+        let inputs: Vec<TransactionInput> = (0..3)
+            .map(|i| {
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(i), i as u32),
+                    ScriptSig::new(Script::new(vec![])),
+                    0xFFFFFFFF,
+                )
+            })
+            .collect();
+        let tx = BitcoinTransaction::new(2, inputs, vec![], 0);
+
+        assert_eq!(tx.spends(&OutPoint::new(dummy_txid(0), 0)), Some(0));
+        assert_eq!(tx.spends(&OutPoint::new(dummy_txid(2), 2)), Some(2));
+        // Same txid, different vout.
+        assert_eq!(tx.spends(&OutPoint::new(dummy_txid(1), 0)), None);
+        assert_eq!(tx.spends(&OutPoint::new(dummy_txid(9), 0)), None);
+    }
+
+    #[test]
+    fn test_base58_check() {
+        let vectors = [
+            (
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+                "0062e907b15cbf27d5425399ebf6f0fb50ebb88f18",
+            ),
+            (
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                "05b472a266d0bd89c13706a4132ccfb16f7c3b9fcb",
+            ),
+            (
+                "1111111111111111111114oLvT2",
+                "000000000000000000000000000000000000000000",
+            ),
+        ];
+        for (address, payload) in vectors {
+            let payload = hex::decode(payload).unwrap();
+            assert_eq!(base58::encode_check(&payload), address);
+            assert_eq!(base58::decode_check(address), Ok(payload));
+        }
+
+        // Last character changed, so the checksum no longer matches.
+        assert_eq!(
+            base58::decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        // '0' is not in the alphabet.
+        assert_eq!(base58::decode_check("10"), Err(BitcoinError::InvalidFormat));
+        assert_eq!(base58::decode_check("1"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_typed_scripts() {
+        let sig = ScriptSig::new(Script::new(vec![0x51]));
+        let pubkey = ScriptPubKey::new(Script::new(vec![0x52]));
+
+        // The fields keep their role, so they move between inputs and outputs
+        // of the right kind without rewrapping.
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), sig.clone(), 0xFFFFFFFF);
+        let output = TransactionOutput::new(1_000, pubkey.clone());
+        assert_eq!(input.script_sig, sig);
+        assert_eq!(output.script_pubkey, pubkey);
+
+        let built = TransactionBuilder::new()
+            .add_input(
+                input.previous_output.clone(),
+                input.script_sig.clone(),
+                0xFFFFFFFF,
+            )
+            .add_output(output.value, output.script_pubkey.clone())
+            .build();
+        assert_eq!(built.inputs[0], input);
+        assert_eq!(built.outputs[0], output);
+
+        // Both wrappers deref to the underlying script and unwrap back into it.
+        assert_eq!(sig.to_bytes(), vec![0x01, 0x51]);
+        assert_eq!(Script::from(pubkey), Script::new(vec![0x52]));
+        assert_eq!(
+            serde_json::to_string(&input.script_sig).unwrap(),
+            serde_json::to_string(&Script::new(vec![0x51])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_script_roundtrip() {
+        let empty = Script::new(vec![]);
+        assert!(empty.is_empty_script());
+        assert!(!Script::new(vec![0x00]).is_empty_script());
+
+        let bytes = empty.to_bytes();
+        assert_eq!(bytes, vec![0x00]);
+        let (parsed, consumed) = Script::from_bytes(&bytes).unwrap();
+        assert!(parsed.is_empty_script());
+        assert_eq!(consumed, 1);
+
+        // The first input of the SegWit vector spends a native witness program.
+        let tx_bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+        assert!(tx.inputs[0].script_sig.is_empty_script());
+    }
+
+    #[test]
+    fn test_script_hash_and_p2sh() {
+        // 1-of-1 multisig over the secp256k1 generator point.
+        let redeem = Script::from_hex(
+            "51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae",
+        )
+        .unwrap();
+        let hash = redeem.script_hash();
+        assert_eq!(
+            hex::encode(hash),
+            "83eebb7d79aa1d388e3b0ac65b98ac580c4da01a"
+        );
+
+        let p2sh = redeem.to_p2sh();
+        assert_eq!(
+            hex::encode(&p2sh.bytes),
+            "a91483eebb7d79aa1d388e3b0ac65b98ac580c4da01a87"
+        );
+        assert_eq!(p2sh.script_type(), ScriptType::P2SH);
+        assert!(p2sh.pays_to_script_hash(&hash));
+        assert_eq!(
+            p2sh.address(Network::Mainnet).as_deref(),
+            Some("3DicS6C8JZm59RsrgXr56iVHzYdQngiehV")
+        );
+    }
+
+    #[test]
+    fn test_is_segwit() {
+        let legacy_bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (legacy, _) = BitcoinTransaction::from_bytes(&legacy_bytes).unwrap();
+        assert!(!legacy.is_segwit());
+        assert_eq!(legacy.to_bytes(), legacy.to_bytes_legacy());
+
+        let segwit_bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (segwit, _) = BitcoinTransaction::from_bytes(&segwit_bytes).unwrap();
+        assert!(segwit.is_segwit());
+        assert!(!segwit.strip_witness().is_segwit());
+
+        // Witness entries that are all empty do not make it SegWit.
+        let mut empty_stacks = legacy.clone();
+        empty_stacks.witnesses = vec![Witness::new(); legacy.inputs.len()];
+        assert!(!empty_stacks.is_segwit());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let tx_bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_limits(&tx_bytes, &DecodeLimits::default()),
+            Ok((tx.clone(), consumed))
+        );
+
+        let exact = DecodeLimits {
+            max_inputs: tx.inputs.len(),
+            max_outputs: tx.outputs.len(),
+            max_script_len: tx
+                .outputs
+                .iter()
+                .map(|o| o.script_pubkey.len())
+                .max()
+                .unwrap(),
+            max_witness_items: tx.witnesses.iter().map(|w| w.len()).max().unwrap(),
+        };
+        assert!(BitcoinTransaction::from_bytes_with_limits(&tx_bytes, &exact).is_ok());
+
+        let tightened = [
+            DecodeLimits {
+                max_inputs: exact.max_inputs - 1,
+                ..exact
+            },
+            DecodeLimits {
+                max_outputs: exact.max_outputs - 1,
+                ..exact
+            },
+            DecodeLimits {
+                max_script_len: exact.max_script_len - 1,
+                ..exact
+            },
+            DecodeLimits {
+                max_witness_items: exact.max_witness_items - 1,
+                ..exact
+            },
+        ];
+        for limits in tightened {
+            assert_eq!(
+                BitcoinTransaction::from_bytes_with_limits(&tx_bytes, &limits),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sha256d() {
+        assert_eq!(
+            hex::encode(sha256d(&[])),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+        assert_eq!(
+            hex::encode(sha256d(b"hello")),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+
+        let tx_bytes = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+        assert_eq!(Txid(sha256d(&tx_bytes)), tx.txid());
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let legacy = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        let segwit = hex::decode(SEGWIT_TX_HEX).unwrap();
+        assert!(BitcoinTransaction::round_trips(&legacy));
+        assert!(BitcoinTransaction::round_trips(&segwit));
+
+        // The scriptSig length 0x48 written as a three-byte CompactSize.
+        let mut non_minimal = legacy[..41].to_vec();
+        non_minimal.extend_from_slice(&[0xFD, 0x48, 0x00]);
+        non_minimal.extend_from_slice(&legacy[42..]);
+        assert!(BitcoinTransaction::from_bytes_exact(&non_minimal).is_ok());
+        assert!(!BitcoinTransaction::round_trips(&non_minimal));
+
+        // Marker and flag with an empty witness re-encode in legacy form.
+        let mut empty_witness = legacy[..4].to_vec();
+        empty_witness.extend_from_slice(&[0x00, 0x01]);
+        empty_witness.extend_from_slice(&legacy[4..legacy.len() - 4]);
+        empty_witness.push(0x00);
+        empty_witness.extend_from_slice(&legacy[legacy.len() - 4..]);
+        assert!(BitcoinTransaction::from_bytes_exact(&empty_witness).is_ok());
+        assert!(!BitcoinTransaction::round_trips(&empty_witness));
+
+        let mut trailing = legacy.clone();
+        trailing.push(0x00);
+        assert!(!BitcoinTransaction::round_trips(&trailing));
+        assert!(!BitcoinTransaction::round_trips(
+            &legacy[..legacy.len() - 1]
+        ));
+    }
+
+    #[test]
+    fn test_vec_decoder() {
+        let inputs: Vec<TransactionInput> = (0..3)
+            .map(|i| {
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(i), 0),
+                    ScriptSig::new(Script::new(vec![i; i as usize])),
+                    0xFFFFFFFF,
+                )
+            })
+            .collect();
+        let tx = BitcoinTransaction::new(2, inputs.clone(), vec![], 0);
+        let bytes = tx.to_bytes();
+
+        let (count, prefix_len) = CompactSize::from_bytes(&bytes[4..]).unwrap();
+        let list = &bytes[4 + prefix_len..];
+        let mut decoder = VecDecoder::new(
+            list,
+            count.to_usize().unwrap(),
+            TransactionInput::from_bytes,
+        );
+        let decoded: Vec<TransactionInput> = decoder.by_ref().map(Result::unwrap).collect();
+        assert_eq!(decoded, inputs);
+        assert_eq!(
+            decoder.consumed(),
+            inputs.iter().map(|i| i.serialized_len()).sum::<usize>()
+        );
+        assert_eq!(decoder.next(), None);
+
+        let prefixed =
+            VecDecoder::from_prefixed(&bytes[4..], TransactionInput::from_bytes).unwrap();
+        assert_eq!(prefixed.map(Result::unwrap).collect::<Vec<_>>(), inputs);
+
+        // A truncated list yields what it can, then one error, then nothing.
+        let cut = inputs[0].serialized_len() + 10;
+        let mut decoder = VecDecoder::new(&list[..cut], 3, TransactionInput::from_bytes);
+        assert_eq!(decoder.next(), Some(Ok(inputs[0].clone())));
+        assert_eq!(decoder.next(), Some(Err(BitcoinError::InsufficientBytes)));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn test_short_slices_error_without_panicking() {
+        let short = BitcoinError::InsufficientBytes;
+        for bytes in [&[0xFD, 0x01][..], &[0xFE, 0x01, 0x02, 0x03], &[0xFF; 8]] {
+            assert_eq!(CompactSize::from_bytes(bytes).unwrap_err(), short);
+        }
+
+        let outpoint = OutPoint::new(dummy_txid(1), 7).to_bytes();
+        let output =
+            TransactionOutput::new(1, ScriptPubKey::new(Script::new(vec![0x51]))).to_bytes();
+        let header = [0u8; BlockHeader::SIZE];
+        let inventory = Inventory::Tx(Txid(dummy_txid(1))).to_bytes();
+        let tx = hex::decode(BLOCK_170_TX_HEX).unwrap();
+        for len in 0..outpoint.len() {
+            assert_eq!(OutPoint::from_bytes(&outpoint[..len]).unwrap_err(), short);
+        }
+        for len in 0..8 {
+            assert_eq!(
+                TransactionOutput::from_bytes(&output[..len]).unwrap_err(),
+                short
+            );
+        }
+        for len in 0..header.len() {
+            assert_eq!(BlockHeader::from_bytes(&header[..len]).unwrap_err(), short);
+        }
+        for len in 0..inventory.len() {
+            assert_eq!(Inventory::from_bytes(&inventory[..len]).unwrap_err(), short);
+        }
+        for len in [0, 3, tx.len() - 4, tx.len() - 1] {
+            assert_eq!(
+                BitcoinTransaction::from_bytes(&tx[..len]).unwrap_err(),
+                short
+            );
+        }
+    }
+
+    #[test]
+    fn test_legacy_sighash_single_without_output() {
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        let inputs: Vec<TransactionInput> = (0..3)
+            .map(|i| {
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(i), 0),
+                    ScriptSig::new(Script::new(vec![])),
+                    0xFFFFFFFF,
+                )
+            })
+            .collect();
+        let outputs = vec![TransactionOutput::new(
+            1_000,
+            ScriptPubKey::new(Script::new(vec![0x51])),
+        )];
+        let script_code = ScriptPubKey::new(Script::new(vec![0x51]));
+
+        let tx = BitcoinTransaction::new(1, inputs.clone(), outputs, 0);
+        for sighash_type in [SighashType::Single, SighashType::SinglePlusAnyoneCanPay] {
+            assert_ne!(legacy_sighash(&tx, 0, &script_code, sighash_type), Ok(one));
+            assert_eq!(legacy_sighash(&tx, 1, &script_code, sighash_type), Ok(one));
+            assert_eq!(legacy_sighash(&tx, 2, &script_code, sighash_type), Ok(one));
+        }
+
+        let no_outputs = BitcoinTransaction::new(1, inputs, vec![], 0);
+        assert_eq!(
+            legacy_sighash(&no_outputs, 0, &script_code, SighashType::Single),
+            Ok(one)
+        );
+        // A missing input is still an error, not the sentinel.
+        assert_eq!(
+            legacy_sighash(&no_outputs, 3, &script_code, SighashType::Single),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_encode_to_slice() {
+        for tx_hex in [BLOCK_170_TX_HEX, SEGWIT_TX_HEX] {
+            let tx_bytes = hex::decode(tx_hex).unwrap();
+            let (tx, _) = BitcoinTransaction::from_bytes(&tx_bytes).unwrap();
+
+            let mut exact = vec![0u8; tx.serialized_len()];
+            assert_eq!(tx.encode_to_slice(&mut exact), Ok(tx_bytes.len()));
+            assert_eq!(exact, tx_bytes);
+
+            // Extra room is left as it was.
+            let mut roomy = vec![0xAA; tx_bytes.len() + 3];
+            assert_eq!(tx.encode_to_slice(&mut roomy), Ok(tx_bytes.len()));
+            assert_eq!(&roomy[..tx_bytes.len()], tx_bytes.as_slice());
+            assert_eq!(&roomy[tx_bytes.len()..], &[0xAA; 3]);
+
+            let mut short = vec![0u8; tx_bytes.len() - 1];
+            assert_eq!(
+                tx.encode_to_slice(&mut short),
+                Err(BitcoinError::InsufficientBytes)
+            );
+            assert!(short.iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_rpc_json_decodes_script_sig_sighash() {
+        let (tx, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX_HEX).unwrap()).unwrap();
+        let sig = "304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d09";
+        let json = tx.to_rpc_json();
+        assert_eq!(json["vin"][0]["scriptSig"]["asm"], format!("{}[ALL]", sig));
+        // Plain `to_asm` leaves the hash type byte in the hex, as `decodescript` does.
+        assert_eq!(tx.inputs[0].script_sig.to_asm(), format!("{}01", sig));
+
+        // Not strict DER (S has a needless leading zero), so no decoding.
+        let mut non_der = vec![0x30, 0x07, 0x02, 0x01, 0x01, 0x02, 0x02, 0x00, 0x01, 0x01];
+        let mut script = vec![non_der.len() as u8];
+        script.append(&mut non_der);
+        let mut input = tx.inputs[0].clone();
+        input.script_sig = ScriptSig::new(Script::new(script));
+        let tx = BitcoinTransaction::new(1, vec![input], tx.outputs.clone(), 0);
+        assert_eq!(
+            tx.to_rpc_json()["vin"][0]["scriptSig"]["asm"],
+            "30070201010202000101"
+        );
+    }
+
+    #[test]
+    fn test_consensus_decode_bounds_untrusted_streams() {
+        use std::io::Read;
+
+        // Version, marker and flag, one input with an empty scriptSig, no
+        // outputs, then the first input's witness item count.
+        let mut prefix = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01];
+        prefix.extend_from_slice(&OutPoint::new(dummy_txid(1), 0).to_bytes());
+        prefix.extend_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+
+        // A count that could never fit is rejected before reading any item.
+        let mut huge_count = prefix.clone();
+        huge_count.extend_from_slice(&[0xFF; 9]);
+        let mut stream = huge_count.as_slice().chain(std::io::repeat(0x00));
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut stream),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Witness::consensus_decode(&mut [0xFF; 9].as_slice().chain(std::io::repeat(0x00))),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // A script length within the cap, fed from an endless stream, runs
+        // into the transaction's byte budget.
+        let mut endless_item = prefix;
+        endless_item.extend_from_slice(&[0x01, 0xFE, 0x00, 0x09, 0x3D, 0x00]);
+        let mut stream = endless_item.as_slice().chain(std::io::repeat(0x00));
+        assert_eq!(
+            BitcoinTransaction::consensus_decode(&mut stream),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // A block whose transactions add up to more than its byte budget.
+        let big_input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            ScriptSig::new(Script::new(vec![0x51; 100])),
+            0,
+        );
+        let tx = BitcoinTransaction::new(1, vec![big_input], vec![], 0).to_bytes();
+        let mut block = vec![0u8; BlockHeader::SIZE];
+        block.extend_from_slice(&CompactSize::new(70_000).to_bytes());
+        for _ in 0..MAX_TRANSACTION_SIZE / tx.len() + 1 {
+            block.extend_from_slice(&tx);
+        }
+        assert_eq!(
+            Block::consensus_decode(&mut block.as_slice()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let limits = DecodeLimits {
+            max_witness_items: 1,
+            ..DecodeLimits::default()
+        };
+        let tx_bytes = hex::decode(SEGWIT_TX_HEX).unwrap();
+        assert_eq!(
+            BitcoinTransaction::consensus_decode_with_limits(&mut tx_bytes.as_slice(), &limits),
+            Err(BitcoinError::InvalidFormat)
+        );
     }
 }